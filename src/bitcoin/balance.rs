@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+
+use bdk_wallet::{
+    bitcoin::{Amount as BdkAmount, OutPoint as BdkOutPoint},
+    chain::{
+        CanonicalizationParams, ChainPosition as BdkChainPosition, ConfirmationBlockTime as BdkConfirmationBlockTime,
+        FullTxOut,
+    },
+    Balance as BdkBalance, KeychainKind as BdkKeychainKind, Wallet as BdkWallet,
+};
+
+use crate::types::{AddressBalance, BalancePoint};
+
+/// Balance of the given outpoints of the wallet, split like [`BdkWallet::balance`].
+pub(crate) fn balance(
+    wallet: &BdkWallet,
+    outpoints: impl IntoIterator<Item = ((BdkKeychainKind, u32), BdkOutPoint)>,
+) -> BdkBalance {
+    wallet.tx_graph().balance(
+        wallet.local_chain(),
+        wallet.local_chain().tip().block_id(),
+        CanonicalizationParams::default(),
+        outpoints,
+        |&(keychain, _), _| keychain == BdkKeychainKind::Internal,
+    )
+}
+
+/// Balance of each revealed address of `keychain`, computed in a single pass over its outputs.
+pub(crate) fn address_balances(wallet: &BdkWallet, keychain: BdkKeychainKind) -> Vec<AddressBalance> {
+    let Some(last_revealed) = wallet.spk_index().last_revealed_index(keychain) else {
+        return vec![];
+    };
+
+    let tip = wallet.local_chain().tip().block_id();
+    let mut balances = vec![BdkBalance::default(); last_revealed as usize + 1];
+    let outpoints = wallet.spk_index().keychain_outpoints(keychain);
+    let unspents = wallet.tx_graph().filter_chain_unspents(
+        wallet.local_chain(),
+        tip,
+        CanonicalizationParams::default(),
+        outpoints,
+    );
+
+    // Same split as `TxGraph::balance`.
+    for (index, txout) in unspents {
+        let Some(balance) = balances.get_mut(index as usize) else {
+            continue;
+        };
+        match txout.chain_position {
+            BdkChainPosition::Confirmed { .. } => {
+                if txout.is_confirmed_and_spendable(tip.height) {
+                    balance.confirmed += txout.txout.value;
+                } else if !txout.is_mature(tip.height) {
+                    balance.immature += txout.txout.value;
+                }
+            }
+            BdkChainPosition::Unconfirmed { .. } => {
+                if keychain == BdkKeychainKind::Internal {
+                    balance.trusted_pending += txout.txout.value;
+                } else {
+                    balance.untrusted_pending += txout.txout.value;
+                }
+            }
+        }
+    }
+
+    balances
+        .into_iter()
+        .enumerate()
+        .map(|(index, balance)| {
+            let address_info = wallet.peek_address(keychain, index as u32);
+            AddressBalance(address_info.into(), balance.into())
+        })
+        .collect()
+}
+
+/// Confirmed balance of the wallet counting only the blocks whose anchor satisfies `is_included`.
+pub(crate) fn confirmed_balance_at(
+    wallet: &BdkWallet,
+    is_included: impl Fn(&BdkConfirmationBlockTime) -> bool,
+) -> BdkAmount {
+    let is_confirmed_in = |chain_position: &BdkChainPosition<BdkConfirmationBlockTime>| match chain_position {
+        BdkChainPosition::Confirmed { anchor, .. } => is_included(anchor),
+        BdkChainPosition::Unconfirmed { .. } => false,
+    };
+
+    txouts(wallet)
+        .filter(|txout| is_confirmed_in(&txout.chain_position))
+        .filter(|txout| {
+            !txout
+                .spent_by
+                .as_ref()
+                .is_some_and(|(chain_position, _)| is_confirmed_in(chain_position))
+        })
+        .map(|txout| txout.txout.value)
+        .sum()
+}
+
+/// Confirmed balance of the wallet after each block changing it, from the oldest to the most recent.
+pub(crate) fn balance_history(wallet: &BdkWallet) -> Vec<BalancePoint> {
+    // Balance changes indexed by height, with the confirmation time of the block.
+    let mut deltas: BTreeMap<u32, (u64, i64)> = BTreeMap::new();
+    let mut apply = |chain_position: &BdkChainPosition<BdkConfirmationBlockTime>, delta: i64| {
+        if let BdkChainPosition::Confirmed { anchor, .. } = chain_position {
+            let entry = deltas
+                .entry(anchor.block_id.height)
+                .or_insert((anchor.confirmation_time, 0));
+            entry.1 += delta;
+        }
+    };
+
+    for txout in txouts(wallet) {
+        let value = txout.txout.value.to_sat() as i64;
+        apply(&txout.chain_position, value);
+        if let Some((chain_position, _)) = &txout.spent_by {
+            apply(chain_position, -value);
+        }
+    }
+
+    let mut balance = 0i64;
+    deltas
+        .into_iter()
+        .filter(|(_, (_, delta))| *delta != 0)
+        .map(|(height, (time, delta))| {
+            balance += delta;
+            BalancePoint::new(height, time, BdkAmount::from_sat(balance.max(0) as u64).into())
+        })
+        .collect()
+}
+
+// Every canonical output of the wallet, spent or not.
+fn txouts(wallet: &BdkWallet) -> impl Iterator<Item = FullTxOut<BdkConfirmationBlockTime>> + '_ {
+    wallet
+        .tx_graph()
+        .filter_chain_txouts(
+            wallet.local_chain(),
+            wallet.local_chain().tip().block_id(),
+            CanonicalizationParams::default(),
+            wallet.spk_index().outpoints().iter().cloned(),
+        )
+        .map(|(_, txout)| txout)
+}
@@ -0,0 +1,173 @@
+use std::fmt;
+
+use bdk_wallet::{
+    bitcoin::{key::rand::RngCore, Amount as BdkAmount, FeeRate as BdkFeeRate, OutPoint as BdkOutPoint, Script, TxIn},
+    coin_selection::{decide_change, CoinSelectionAlgorithm, CoinSelectionResult, InsufficientFunds},
+    Utxo, WeightedUtxo,
+};
+use wasm_bindgen::{convert::TryFromJsValue, prelude::wasm_bindgen, JsCast, JsValue};
+use web_sys::js_sys::{Array, Function};
+
+use crate::{
+    types::{Amount, BdkError, BdkErrorCode, FeeRate, LocalOutput, OutPoint},
+    utils::{js_error_message, ErrorStash},
+};
+
+/// Coin selection algorithm used to choose the inputs of a transaction.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Default)]
+pub enum CoinSelection {
+    /// Branch and bound, looking for an input set that avoids a change output.
+    /// Falls back to single random draw (default)
+    #[default]
+    BranchAndBound,
+    /// Picks the largest UTXOs first
+    LargestFirst,
+    /// Picks the oldest UTXOs first
+    OldestFirst,
+    /// Picks UTXOs at random until the target amount is reached
+    SingleRandomDraw,
+}
+
+/// Coin selection delegated to a JS function.
+///
+/// The function is called with the candidate `LocalOutput`s, the `Amount` the selection must cover
+/// and the `FeeRate` of the transaction, and returns the `OutPoint`s to spend. The UTXOs that must be
+/// spent are always selected and are not part of the candidates.
+///
+/// Errors of the function are kept in an [`ErrorStash`], retrieved with `take_error`.
+#[derive(Clone)]
+pub(crate) struct JsCoinSelection {
+    selector: Function,
+    error: ErrorStash,
+}
+
+impl JsCoinSelection {
+    pub(crate) fn new(selector: Function) -> Self {
+        JsCoinSelection {
+            selector,
+            error: ErrorStash::default(),
+        }
+    }
+
+    pub(crate) fn take_error(&self) -> Option<BdkError> {
+        self.error.take()
+    }
+
+    fn select(
+        &self,
+        candidates: &[WeightedUtxo],
+        target: BdkAmount,
+        fee_rate: BdkFeeRate,
+    ) -> Result<Vec<BdkOutPoint>, BdkError> {
+        let js_candidates: Array = candidates
+            .iter()
+            .filter_map(|candidate| match &candidate.utxo {
+                Utxo::Local(local) => Some(JsValue::from(LocalOutput::from(local.clone()))),
+                Utxo::Foreign { .. } => None,
+            })
+            .collect();
+
+        let selected = self
+            .selector
+            .call3(
+                &JsValue::NULL,
+                &js_candidates,
+                &Amount::from(target).into(),
+                &FeeRate::from(fee_rate).into(),
+            )
+            .map_err(|e| BdkError::new(BdkErrorCode::CoinSelectionFailed, js_error_message(&e), ()))?;
+
+        let selected = selected.dyn_into::<Array>().map_err(|_| {
+            BdkError::new(
+                BdkErrorCode::CoinSelectionFailed,
+                "Coin selection must return an array of outpoints",
+                (),
+            )
+        })?;
+
+        selected
+            .iter()
+            .map(|value| {
+                OutPoint::try_from_js_value(value).map(Into::into).map_err(|_| {
+                    BdkError::new(
+                        BdkErrorCode::CoinSelectionFailed,
+                        "Coin selection must return an array of outpoints",
+                        (),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for JsCoinSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("JsCoinSelection")
+    }
+}
+
+impl CoinSelectionAlgorithm for JsCoinSelection {
+    fn coin_select<R: RngCore>(
+        &self,
+        required_utxos: Vec<WeightedUtxo>,
+        mut optional_utxos: Vec<WeightedUtxo>,
+        fee_rate: BdkFeeRate,
+        target_amount: BdkAmount,
+        drain_script: &Script,
+        _: &mut R,
+    ) -> Result<CoinSelectionResult, InsufficientFunds> {
+        let input_fee = |utxo: &WeightedUtxo| fee_rate * (TxIn::default().segwit_weight() + utxo.satisfaction_weight);
+
+        let required_fee: BdkAmount = required_utxos.iter().map(input_fee).sum();
+        let required_amount: BdkAmount = required_utxos.iter().map(|utxo| utxo.utxo.txout().value).sum();
+        let target = (target_amount + required_fee)
+            .checked_sub(required_amount)
+            .unwrap_or_default();
+
+        let outpoints = match self.select(&optional_utxos, target, fee_rate) {
+            Ok(outpoints) => outpoints,
+            Err(e) => {
+                self.error.set(e);
+                return Err(InsufficientFunds {
+                    needed: target_amount,
+                    available: BdkAmount::ZERO,
+                });
+            }
+        };
+
+        let mut selected = required_utxos;
+        for outpoint in outpoints {
+            match optional_utxos.iter().position(|utxo| utxo.utxo.outpoint() == outpoint) {
+                Some(index) => selected.push(optional_utxos.swap_remove(index)),
+                None => {
+                    self.error.set(BdkError::new(
+                        BdkErrorCode::UnknownUtxo,
+                        format!("Coin selection returned an outpoint that is not a candidate: {outpoint}"),
+                        outpoint,
+                    ));
+                    return Err(InsufficientFunds {
+                        needed: target_amount,
+                        available: BdkAmount::ZERO,
+                    });
+                }
+            }
+        }
+
+        let fee_amount: BdkAmount = selected.iter().map(input_fee).sum();
+        let selected_amount: BdkAmount = selected.iter().map(|utxo| utxo.utxo.txout().value).sum();
+        let needed = target_amount + fee_amount;
+        if selected_amount < needed {
+            return Err(InsufficientFunds {
+                needed,
+                available: selected_amount,
+            });
+        }
+
+        Ok(CoinSelectionResult {
+            selected: selected.into_iter().map(|utxo| utxo.utxo).collect(),
+            fee_amount,
+            excess: decide_change(selected_amount - needed, fee_rate, drain_script),
+        })
+    }
+}
@@ -1,60 +1,114 @@
-use bdk_esplora::{
-    esplora_client::{AsyncClient, Builder},
-    EsploraAsyncExt,
-};
+use std::collections::BTreeMap;
+
 use bdk_wallet::{
     chain::spk_client::{FullScanRequest as BdkFullScanRequest, SyncRequest as BdkSyncRequest},
     KeychainKind,
 };
-use wasm_bindgen::{
-    prelude::{wasm_bindgen, Closure},
-    JsCast, JsValue,
-};
-use wasm_bindgen_futures::JsFuture;
-use web_sys::js_sys::{Function, Promise};
+use wasm_bindgen::{prelude::wasm_bindgen, JsError};
+use web_sys::{js_sys::Function, AbortSignal};
 
 use crate::{
     result::JsResult,
     types::{FeeEstimates, FullScanRequest, SyncRequest, Transaction, Txid, Update},
 };
-use std::{
-    future::Future,
-    pin::Pin,
-    task::{Context, Poll},
-    time::Duration,
+
+use super::{
+    esplora_fetch::{global_function, set_timeout, FetchClient},
+    esplora_scan,
 };
 
-use bdk_esplora::esplora_client::Sleeper;
+/// Default number of retries of a failed request.
+const DEFAULT_MAX_RETRIES: usize = 6;
 
 #[wasm_bindgen]
 pub struct EsploraClient {
-    client: AsyncClient<WebSleeper>,
+    client: FetchClient,
 }
 
 #[wasm_bindgen]
 impl EsploraClient {
+    /// Create a client of the Esplora server at `url`, retrying failed requests up to `max_retries` times.
+    ///
+    /// Retries wait with an exponential backoff using the `setTimeout` function of `globalThis`, which
+    /// is available in browsers, Node.js and Web Workers. Fails if retries are requested in an
+    /// environment without it.
     #[wasm_bindgen(constructor)]
     pub fn new(url: &str, max_retries: usize) -> JsResult<EsploraClient> {
-        let client = Builder::new(url)
-            .max_retries(max_retries)
-            .build_async_with_sleeper::<WebSleeper>()?;
+        let options = EsploraClientOptions {
+            max_retries,
+            ..EsploraClientOptions::new()
+        };
+        EsploraClient::with_options(url, &options)
+    }
+
+    /// Create a client of the Esplora server at `url` sending its requests as configured by `options`.
+    ///
+    /// Every request goes through the `fetch` function of the options, or the global `fetch` if not set.
+    /// Retries and timeouts both rely on the global `setTimeout` function, so this fails if either is
+    /// requested in an environment without it.
+    pub fn with_options(url: &str, options: &EsploraClientOptions) -> JsResult<EsploraClient> {
+        if options.max_retries > 0 && set_timeout().is_none() {
+            return Err(JsError::new(
+                "Retries require a global setTimeout function, use max_retries = 0 in this environment",
+            ));
+        }
+        if options.timeout.is_some() && set_timeout().is_none() {
+            return Err(JsError::new(
+                "Timeouts require a global setTimeout function, leave the timeout unset in this environment",
+            ));
+        }
+
+        let fetch = options
+            .fetch
+            .clone()
+            .or_else(|| global_function("fetch"))
+            .ok_or_else(|| JsError::new("No global fetch function, set one in the options of the client"))?;
+
+        let client = FetchClient::new(
+            url,
+            fetch,
+            options.headers.clone(),
+            options.timeout,
+            options.signal.clone(),
+            options.max_retries,
+        );
         Ok(EsploraClient { client })
     }
 
+    /// Scan the keychains of `request` until `stop_gap` consecutive scripts have no transactions,
+    /// or the stop gap of the keychain set in the request, sending up to `parallel_requests` requests
+    /// at once.
+    ///
+    /// Aborting `signal`, if any, aborts the in-flight requests and rejects with an error.
     pub async fn full_scan(
         &self,
         request: FullScanRequest,
         stop_gap: usize,
         parallel_requests: usize,
+        signal: Option<AbortSignal>,
     ) -> JsResult<Update> {
+        let stop_gaps: BTreeMap<KeychainKind, usize> = BdkFullScanRequest::keychains(&request)
+            .into_iter()
+            .filter_map(|keychain| Some((keychain, request.stop_gap(keychain.into())?)))
+            .collect();
         let request: BdkFullScanRequest<KeychainKind> = request.into();
-        let result = self.client.full_scan(request, stop_gap, parallel_requests).await?;
+        let client = self.client_with(signal);
+        let result = esplora_scan::full_scan(&client, request, stop_gap, &stop_gaps, parallel_requests).await?;
         Ok(result.into())
     }
 
-    pub async fn sync(&self, request: SyncRequest, parallel_requests: usize) -> JsResult<Update> {
+    /// Sync the scripts, txids and outpoints of `request`, sending up to `parallel_requests` requests at once.
+    ///
+    /// Aborting `signal`, if any, aborts the in-flight requests and rejects with an error.
+    pub async fn sync(
+        &self,
+        request: SyncRequest,
+        parallel_requests: usize,
+        signal: Option<AbortSignal>,
+    ) -> JsResult<Update> {
         let request: BdkSyncRequest<(KeychainKind, u32)> = request.into();
-        let result = self.client.sync(request, parallel_requests).await?;
+        let client = self.client_with(signal);
+        let result = esplora_scan::sync(&client, request, parallel_requests).await?;
         Ok(result.into())
     }
 
@@ -74,34 +128,98 @@ impl EsploraClient {
     }
 }
 
-struct WebSleep(JsFuture);
+impl EsploraClient {
+    fn client_with(&self, signal: Option<AbortSignal>) -> FetchClient {
+        match signal {
+            Some(signal) => self.client.with_signal(signal),
+            None => self.client.clone(),
+        }
+    }
+}
+
+/// Options of [`EsploraClient::with_options`], configuring how requests are sent.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct EsploraClientOptions {
+    fetch: Option<Function>,
+    headers: Vec<(String, String)>,
+    timeout: Option<u32>,
+    signal: Option<AbortSignal>,
+    max_retries: usize,
+}
+
+#[wasm_bindgen]
+impl EsploraClientOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        EsploraClientOptions {
+            fetch: None,
+            headers: vec![],
+            timeout: None,
+            signal: None,
+            max_retries: DEFAULT_MAX_RETRIES,
+        }
+    }
+
+    /// Function sending the requests, with the signature of the standard `fetch` (global `fetch` if not set).
+    ///
+    /// It is called with the URL and an init object holding the `method`, `headers`, `body` and
+    /// `signal` of the request, and must resolve to a response with a numeric `status` and a `text()`
+    /// method. This allows adding authentication, proxying or logging requests, or answering them
+    /// locally in tests.
+    #[wasm_bindgen(getter)]
+    pub fn fetch(&self) -> Option<Function> {
+        self.fetch.clone()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_fetch(&mut self, fetch: Option<Function>) {
+        self.fetch = fetch;
+    }
+
+    /// Header sent with every request, replacing the previous value of the header if any
+    pub fn set_header(&mut self, name: String, value: String) {
+        self.headers
+            .retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+        self.headers.push((name, value));
+    }
+
+    /// Time in milliseconds after which a request is aborted, no timeout if not set
+    #[wasm_bindgen(getter)]
+    pub fn timeout(&self) -> Option<u32> {
+        self.timeout
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_timeout(&mut self, timeout: Option<u32>) {
+        self.timeout = timeout;
+    }
+
+    /// Signal aborting the in-flight requests of the client, and failing the next ones once aborted
+    #[wasm_bindgen(getter)]
+    pub fn signal(&self) -> Option<AbortSignal> {
+        self.signal.clone()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_signal(&mut self, signal: Option<AbortSignal>) {
+        self.signal = signal;
+    }
+
+    /// Number of retries of a request failing with a retryable status (default `6`)
+    #[wasm_bindgen(getter)]
+    pub fn max_retries(&self) -> usize {
+        self.max_retries
+    }
 
-impl Future for WebSleep {
-    type Output = ();
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
-        // delegate to the inner JsFuture
-        Pin::new(&mut self.get_mut().0).poll(cx).map(|_| ())
+    #[wasm_bindgen(setter)]
+    pub fn set_max_retries(&mut self, max_retries: usize) {
+        self.max_retries = max_retries;
     }
 }
 
-// SAFETY: Wasm is single-threaded; the value is never accessed concurrently.
-unsafe impl Send for WebSleep {}
-
-#[derive(Clone, Copy)]
-struct WebSleeper;
-
-impl Sleeper for WebSleeper {
-    type Sleep = WebSleep;
-
-    fn sleep(dur: Duration) -> Self::Sleep {
-        let ms = dur.as_millis() as i32;
-        let promise = Promise::new(&mut |resolve, _reject| {
-            let cb = Closure::once_into_js(move || resolve.call0(&JsValue::NULL).unwrap());
-            web_sys::window()
-                .unwrap()
-                .set_timeout_with_callback_and_timeout_and_arguments_0(cb.unchecked_ref::<Function>(), ms)
-                .unwrap();
-        });
-        WebSleep(JsFuture::from(promise))
+impl Default for EsploraClientOptions {
+    fn default() -> Self {
+        Self::new()
     }
 }
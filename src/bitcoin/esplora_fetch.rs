@@ -0,0 +1,288 @@
+use std::{collections::HashMap, time::Duration};
+
+use bdk_esplora::esplora_client::{BlockSummary, OutputStatus, Tx};
+use bdk_wallet::{
+    bitcoin::{
+        consensus::encode::{deserialize_hex, serialize_hex},
+        hashes::{sha256, Hash},
+        BlockHash, Script, Transaction as BdkTransaction, Txid as BdkTxid,
+    },
+    serde_json,
+};
+use serde::de::DeserializeOwned;
+use wasm_bindgen::{JsCast, JsError, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    js_sys::{global, Error, Function, Object, Promise, Reflect},
+    AbortController, AbortSignal,
+};
+
+use crate::{result::JsResult, utils::js_error_message};
+
+/// Response status codes for which a request may be retried.
+const RETRYABLE_STATUSES: [u16; 3] = [429, 500, 503];
+
+/// Delay before the first retry, doubled at each attempt.
+const BASE_BACKOFF: Duration = Duration::from_millis(256);
+
+/// Client of the Esplora HTTP API sending every request through a JS `fetch` function.
+///
+/// The function is called like the standard `fetch`, with the URL and an init object holding the
+/// `method`, `headers`, `body` and `signal` of the request. It must return a promise of a response
+/// with a numeric `status` and a `text()` method.
+#[derive(Clone)]
+pub(crate) struct FetchClient {
+    url: String,
+    fetch: Function,
+    headers: Vec<(String, String)>,
+    timeout: Option<u32>,
+    signals: Vec<AbortSignal>,
+    max_retries: usize,
+}
+
+struct FetchResponse {
+    status: u16,
+    body: String,
+}
+
+impl FetchClient {
+    pub(crate) fn new(
+        url: &str,
+        fetch: Function,
+        headers: Vec<(String, String)>,
+        timeout: Option<u32>,
+        signal: Option<AbortSignal>,
+        max_retries: usize,
+    ) -> Self {
+        FetchClient {
+            url: url.to_string(),
+            fetch,
+            headers,
+            timeout,
+            signals: signal.into_iter().collect(),
+            max_retries,
+        }
+    }
+
+    /// This client with its requests also aborted by `signal`.
+    pub(crate) fn with_signal(&self, signal: AbortSignal) -> Self {
+        let mut client = self.clone();
+        client.signals.push(signal);
+        client
+    }
+
+    /// Recent block summaries, starting at the tip.
+    pub(crate) async fn get_blocks(&self) -> JsResult<Vec<BlockSummary>> {
+        let blocks: Vec<BlockSummary> = self.get_json("/blocks").await?;
+        if blocks.is_empty() {
+            return Err(JsError::new("Esplora returned no blocks"));
+        }
+        Ok(blocks)
+    }
+
+    pub(crate) async fn get_block_hash(&self, height: u32) -> JsResult<BlockHash> {
+        let hash = self.get_text(&format!("/block-height/{height}")).await?;
+        Ok(hash.trim().parse()?)
+    }
+
+    /// Confirmed transactions of a script, 25 per page, after the `last_seen` transaction if any.
+    pub(crate) async fn scripthash_txs(&self, script: &Script, last_seen: Option<BdkTxid>) -> JsResult<Vec<Tx>> {
+        let script_hash = sha256::Hash::hash(script.as_bytes());
+        let path = match last_seen {
+            Some(last_seen) => format!("/scripthash/{script_hash:x}/txs/chain/{last_seen}"),
+            None => format!("/scripthash/{script_hash:x}/txs"),
+        };
+        self.get_json(&path).await
+    }
+
+    pub(crate) async fn get_tx_info(&self, txid: &BdkTxid) -> JsResult<Option<Tx>> {
+        self.get_opt_json(&format!("/tx/{txid}")).await
+    }
+
+    pub(crate) async fn get_output_status(&self, txid: &BdkTxid, vout: u32) -> JsResult<Option<OutputStatus>> {
+        self.get_opt_json(&format!("/tx/{txid}/outspend/{vout}")).await
+    }
+
+    pub(crate) async fn get_tx(&self, txid: &BdkTxid) -> JsResult<Option<BdkTransaction>> {
+        match self.get_opt_text(&format!("/tx/{txid}/hex")).await? {
+            Some(hex) => Ok(Some(deserialize_hex(hex.trim())?)),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) async fn get_fee_estimates(&self) -> JsResult<HashMap<u16, f64>> {
+        self.get_json("/fee-estimates").await
+    }
+
+    pub(crate) async fn broadcast(&self, tx: &BdkTransaction) -> JsResult<()> {
+        let response = self.send("POST", "/tx", Some(serialize_hex(tx))).await?;
+        check_status(response).map(|_| ())
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, path: &str) -> JsResult<T> {
+        let body = self.get_text(path).await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    async fn get_opt_json<T: DeserializeOwned>(&self, path: &str) -> JsResult<Option<T>> {
+        match self.get_opt_text(path).await? {
+            Some(body) => Ok(Some(serde_json::from_str(&body)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_text(&self, path: &str) -> JsResult<String> {
+        let response = self.get_with_retry(path).await?;
+        check_status(response)
+    }
+
+    async fn get_opt_text(&self, path: &str) -> JsResult<Option<String>> {
+        let response = self.get_with_retry(path).await?;
+        if response.status == 404 {
+            return Ok(None);
+        }
+        check_status(response).map(Some)
+    }
+
+    /// Sends a GET request, retrying with an exponential backoff while the status is retryable.
+    async fn get_with_retry(&self, path: &str) -> JsResult<FetchResponse> {
+        let mut delay = BASE_BACKOFF;
+        let mut attempts = 0;
+
+        loop {
+            match self.send("GET", path, None).await? {
+                response if attempts < self.max_retries && RETRYABLE_STATUSES.contains(&response.status) => {
+                    sleep(delay).await;
+                    attempts += 1;
+                    delay *= 2;
+                }
+                response => return Ok(response),
+            }
+        }
+    }
+
+    /// Sends a single request, aborted when one of the client's signals is or after the timeout.
+    async fn send(&self, method: &str, path: &str, body: Option<String>) -> JsResult<FetchResponse> {
+        let url = format!("{}{}", self.url, path);
+        if self.is_aborted() {
+            return Err(JsError::new(&format!("Request to {url} was aborted")));
+        }
+
+        let controller = AbortController::new().map_err(|e| js_error("Failed to create an AbortController", e))?;
+        let abort = Reflect::get(&controller, &JsValue::from_str("abort"))
+            .ok()
+            .and_then(|abort| abort.dyn_into::<Function>().ok())
+            .map(|abort| abort.bind(&controller))
+            .ok_or_else(|| JsError::new("AbortController has no abort method"))?;
+
+        for signal in &self.signals {
+            let _ = signal.add_event_listener_with_callback("abort", &abort);
+        }
+        let timer = self.timeout.and_then(|timeout| {
+            let set_timeout = set_timeout()?;
+            set_timeout.call2(&global(), &abort, &JsValue::from(timeout)).ok()
+        });
+
+        let result = self.fetch(&url, method, body, &controller.signal()).await;
+
+        for signal in &self.signals {
+            let _ = signal.remove_event_listener_with_callback("abort", &abort);
+        }
+        if let (Some(timer), Some(clear_timeout)) = (timer, global_function("clearTimeout")) {
+            let _ = clear_timeout.call1(&global(), &timer);
+        }
+
+        result.map_err(|e| {
+            if self.is_aborted() {
+                JsError::new(&format!("Request to {url} was aborted"))
+            } else if controller.signal().aborted() {
+                JsError::new(&format!(
+                    "Request to {url} timed out after {} ms",
+                    self.timeout.unwrap_or_default()
+                ))
+            } else {
+                js_error(&format!("Request to {url} failed"), e)
+            }
+        })
+    }
+
+    fn is_aborted(&self) -> bool {
+        self.signals.iter().any(AbortSignal::aborted)
+    }
+
+    async fn fetch(
+        &self,
+        url: &str,
+        method: &str,
+        body: Option<String>,
+        signal: &AbortSignal,
+    ) -> Result<FetchResponse, JsValue> {
+        let headers = Object::new();
+        for (name, value) in &self.headers {
+            Reflect::set(&headers, &JsValue::from_str(name), &JsValue::from_str(value))?;
+        }
+
+        let init = Object::new();
+        Reflect::set(&init, &JsValue::from_str("method"), &JsValue::from_str(method))?;
+        Reflect::set(&init, &JsValue::from_str("headers"), &headers)?;
+        if let Some(body) = body {
+            Reflect::set(&init, &JsValue::from_str("body"), &JsValue::from(body))?;
+        }
+        Reflect::set(&init, &JsValue::from_str("signal"), signal)?;
+
+        let response = self.fetch.call2(&global(), &JsValue::from_str(url), &init)?;
+        let response = JsFuture::from(Promise::resolve(&response)).await?;
+
+        let status = Reflect::get(&response, &JsValue::from_str("status"))?
+            .as_f64()
+            .ok_or_else(|| JsValue::from(Error::new("Response has no numeric status")))?;
+        let text = Reflect::get(&response, &JsValue::from_str("text"))?.dyn_into::<Function>()?;
+        let body = JsFuture::from(Promise::resolve(&text.call0(&response)?)).await?;
+
+        Ok(FetchResponse {
+            status: status as u16,
+            body: body.as_string().unwrap_or_default(),
+        })
+    }
+}
+
+fn check_status(response: FetchResponse) -> JsResult<String> {
+    if (200..300).contains(&response.status) {
+        Ok(response.body)
+    } else {
+        Err(JsError::new(&format!(
+            "Esplora responded with status {}: {}",
+            response.status, response.body
+        )))
+    }
+}
+
+fn js_error(context: &str, error: JsValue) -> JsError {
+    JsError::new(&format!("{context}: {}", js_error_message(&error)))
+}
+
+/// Waits for `duration` using the `setTimeout` function of `globalThis`, which is available in
+/// browsers, Node.js and Web Workers. Resolves right away if there is no timer.
+pub(crate) async fn sleep(duration: Duration) {
+    let ms = duration.as_millis().min(i32::MAX as u128) as i32;
+    let promise = Promise::new(&mut |resolve, _reject| {
+        let scheduled = set_timeout()
+            .map(|set_timeout| set_timeout.call2(&global(), &resolve, &JsValue::from(ms)).is_ok())
+            .unwrap_or(false);
+        if !scheduled {
+            let _ = resolve.call0(&JsValue::NULL);
+        }
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
+pub(crate) fn set_timeout() -> Option<Function> {
+    global_function("setTimeout")
+}
+
+pub(crate) fn global_function(name: &str) -> Option<Function> {
+    Reflect::get(&global(), &JsValue::from_str(name))
+        .ok()?
+        .dyn_into::<Function>()
+        .ok()
+}
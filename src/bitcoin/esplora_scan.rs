@@ -0,0 +1,448 @@
+//! Full scan and sync through the [`FetchClient`], ported from `async_ext.rs` of `bdk_esplora` 0.22.0.
+//!
+//! Changes to the upstream scan logic should be ported here to keep both in sync. Unlike upstream, the
+//! failures caused by the server, such as a different genesis block, are returned as errors.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use bdk_esplora::esplora_client::{TxStatus, Vin};
+use bdk_wallet::{
+    bitcoin::{Amount as BdkAmount, BlockHash, OutPoint as BdkOutPoint, TxOut as BdkTxOut, Txid as BdkTxid},
+    chain::{
+        spk_client::{
+            FullScanRequest as BdkFullScanRequest, FullScanResponse as BdkFullScanResponse, SpkWithExpectedTxids,
+            SyncRequest as BdkSyncRequest, SyncResponse as BdkSyncResponse,
+        },
+        BlockId, CheckPoint, ConfirmationBlockTime as BdkConfirmationBlockTime, Indexed, TxUpdate,
+    },
+};
+use futures::{stream::FuturesOrdered, TryStreamExt};
+use wasm_bindgen::JsError;
+
+use crate::result::JsResult;
+
+use super::esplora_fetch::FetchClient;
+
+/// Number of transactions per page of the Esplora script history.
+const TXS_PER_PAGE: usize = 25;
+
+/// Full scan of the keychains of `request`, as done by `bdk_esplora` but through the [`FetchClient`].
+///
+/// The keychains in `stop_gaps` are scanned with their own stop gap instead of `stop_gap`.
+pub(crate) async fn full_scan<K: Ord + Clone>(
+    client: &FetchClient,
+    mut request: BdkFullScanRequest<K>,
+    stop_gap: usize,
+    stop_gaps: &BTreeMap<K, usize>,
+    parallel_requests: usize,
+) -> JsResult<BdkFullScanResponse<K>> {
+    let start_time = request.start_time();
+    let keychains = request.keychains();
+    let chain_tip = request.chain_tip();
+    let latest_blocks = match chain_tip {
+        Some(_) => Some(fetch_latest_blocks(client).await?),
+        None => None,
+    };
+
+    let mut tx_update = TxUpdate::<BdkConfirmationBlockTime>::default();
+    let mut inserted_txs = HashSet::<BdkTxid>::new();
+    let mut last_active_indices = BTreeMap::<K, u32>::new();
+    for keychain in keychains {
+        let keychain_spks = request
+            .iter_spks(keychain.clone())
+            .map(|(spk_i, spk)| (spk_i, spk.into()));
+        let (update, last_active_index) = fetch_txs_with_keychain_spks(
+            client,
+            start_time,
+            &mut inserted_txs,
+            keychain_spks,
+            stop_gaps.get(&keychain).copied().unwrap_or(stop_gap),
+            parallel_requests,
+        )
+        .await?;
+        tx_update.extend(update);
+        if let Some(last_active_index) = last_active_index {
+            last_active_indices.insert(keychain, last_active_index);
+        }
+    }
+
+    let chain_update = match (chain_tip, latest_blocks) {
+        (Some(chain_tip), Some(latest_blocks)) => {
+            Some(chain_update(client, &latest_blocks, &chain_tip, &tx_update.anchors).await?)
+        }
+        _ => None,
+    };
+
+    Ok(BdkFullScanResponse {
+        chain_update,
+        tx_update,
+        last_active_indices,
+    })
+}
+
+/// Sync of the scripts, txids and outpoints of `request`, as done by `bdk_esplora` but through the
+/// [`FetchClient`].
+pub(crate) async fn sync<I>(
+    client: &FetchClient,
+    mut request: BdkSyncRequest<I>,
+    parallel_requests: usize,
+) -> JsResult<BdkSyncResponse> {
+    let start_time = request.start_time();
+    let chain_tip = request.chain_tip();
+    let latest_blocks = match chain_tip {
+        Some(_) => Some(fetch_latest_blocks(client).await?),
+        None => None,
+    };
+
+    let mut tx_update = TxUpdate::<BdkConfirmationBlockTime>::default();
+    let mut inserted_txs = HashSet::<BdkTxid>::new();
+    tx_update.extend(
+        fetch_txs_with_spks(
+            client,
+            start_time,
+            &mut inserted_txs,
+            request.iter_spks_with_expected_txids(),
+            parallel_requests,
+        )
+        .await?,
+    );
+    tx_update.extend(
+        fetch_txs_with_txids(
+            client,
+            start_time,
+            &mut inserted_txs,
+            request.iter_txids(),
+            parallel_requests,
+        )
+        .await?,
+    );
+    tx_update.extend(
+        fetch_txs_with_outpoints(
+            client,
+            start_time,
+            &mut inserted_txs,
+            request.iter_outpoints(),
+            parallel_requests,
+        )
+        .await?,
+    );
+
+    let chain_update = match (chain_tip, latest_blocks) {
+        (Some(chain_tip), Some(latest_blocks)) => {
+            Some(chain_update(client, &latest_blocks, &chain_tip, &tx_update.anchors).await?)
+        }
+        _ => None,
+    };
+
+    Ok(BdkSyncResponse {
+        chain_update,
+        tx_update,
+    })
+}
+
+async fn fetch_latest_blocks(client: &FetchClient) -> JsResult<BTreeMap<u32, BlockHash>> {
+    Ok(client
+        .get_blocks()
+        .await?
+        .into_iter()
+        .map(|block| (block.time.height, block.id))
+        .collect())
+}
+
+/// Hash of the block at `height`, `None` if above the latest blocks fetched at the start of the scan.
+async fn fetch_block(
+    client: &FetchClient,
+    latest_blocks: &BTreeMap<u32, BlockHash>,
+    height: u32,
+) -> JsResult<Option<BlockHash>> {
+    if let Some(&hash) = latest_blocks.get(&height) {
+        return Ok(Some(hash));
+    }
+
+    // Blocks above the latest blocks are not fetched since the tip signals how far the update goes.
+    let &tip_height = latest_blocks
+        .keys()
+        .last()
+        .ok_or_else(|| JsError::new("Unexpected error: Esplora returned no latest blocks"))?;
+    if height > tip_height {
+        return Ok(None);
+    }
+
+    Ok(Some(client.get_block_hash(height).await?))
+}
+
+/// Checkpoints connecting the local chain to the remote one, including the blocks of the anchors.
+async fn chain_update(
+    client: &FetchClient,
+    latest_blocks: &BTreeMap<u32, BlockHash>,
+    local_tip: &CheckPoint,
+    anchors: &BTreeSet<(BdkConfirmationBlockTime, BdkTxid)>,
+) -> JsResult<CheckPoint> {
+    let mut point_of_agreement = None;
+    let mut conflicts = vec![];
+    for local_cp in local_tip.iter() {
+        let remote_hash = match fetch_block(client, latest_blocks, local_cp.height()).await? {
+            Some(hash) => hash,
+            None => continue,
+        };
+        if remote_hash == local_cp.hash() {
+            point_of_agreement = Some(local_cp.clone());
+            break;
+        }
+        // Every conflicting height is kept so that the update is a superset of the local chain.
+        conflicts.push(BlockId {
+            height: local_cp.height(),
+            hash: remote_hash,
+        });
+    }
+
+    // Without a point of agreement, the server is on another network than the wallet.
+    let mut tip = point_of_agreement
+        .ok_or_else(|| JsError::new("Esplora has a different genesis block than the wallet, check its network"))?;
+    tip = tip
+        .extend(conflicts.into_iter().rev())
+        .map_err(|_| JsError::new("Failed to connect the blocks of Esplora to the local chain"))?;
+
+    for (anchor, _) in anchors {
+        let height = anchor.block_id.height;
+        if tip.get(height).is_none() {
+            let hash = match fetch_block(client, latest_blocks, height).await? {
+                Some(hash) => hash,
+                None => continue,
+            };
+            tip = tip.insert(BlockId { height, hash });
+        }
+    }
+
+    // The most recent blocks make sure the tip is updated.
+    for (&height, &hash) in latest_blocks.iter() {
+        tip = tip.insert(BlockId { height, hash });
+    }
+
+    Ok(tip)
+}
+
+/// Transactions of the scripts of a keychain until `stop_gap` consecutive scripts have none,
+/// along with the index of the last script having transactions.
+async fn fetch_txs_with_keychain_spks(
+    client: &FetchClient,
+    start_time: u64,
+    inserted_txs: &mut HashSet<BdkTxid>,
+    mut keychain_spks: impl Iterator<Item = Indexed<SpkWithExpectedTxids>>,
+    stop_gap: usize,
+    parallel_requests: usize,
+) -> JsResult<(TxUpdate<BdkConfirmationBlockTime>, Option<u32>)> {
+    let mut update = TxUpdate::<BdkConfirmationBlockTime>::default();
+    let mut last_index = Option::<u32>::None;
+    let mut last_active_index = Option::<u32>::None;
+
+    loop {
+        let handles = keychain_spks
+            .by_ref()
+            .take(parallel_requests)
+            .map(|(spk_index, spk)| async move {
+                let mut last_seen = None;
+                let mut spk_txs = Vec::new();
+                loop {
+                    let txs = client.scripthash_txs(&spk.spk, last_seen).await?;
+                    let tx_count = txs.len();
+                    last_seen = txs.last().map(|tx| tx.txid);
+                    spk_txs.extend(txs);
+                    if tx_count < TXS_PER_PAGE {
+                        break;
+                    }
+                }
+                let got_txids = spk_txs.iter().map(|tx| tx.txid).collect::<HashSet<_>>();
+                let evicted_txids = spk
+                    .expected_txids
+                    .difference(&got_txids)
+                    .copied()
+                    .collect::<HashSet<_>>();
+                JsResult::Ok((spk_index, spk_txs, evicted_txids))
+            })
+            .collect::<FuturesOrdered<_>>();
+
+        if handles.is_empty() {
+            break;
+        }
+
+        for (index, txs, evicted) in handles.try_collect::<Vec<_>>().await? {
+            last_index = Some(index);
+            if !txs.is_empty() {
+                last_active_index = Some(index);
+            }
+            for tx in txs {
+                if inserted_txs.insert(tx.txid) {
+                    update.txs.push(tx.to_tx().into());
+                }
+                insert_anchor_or_seen_at_from_status(&mut update, start_time, tx.txid, tx.status);
+                insert_prevouts(&mut update, tx.vin);
+            }
+            update
+                .evicted_ats
+                .extend(evicted.into_iter().map(|txid| (txid, start_time)));
+        }
+
+        let last_index = last_index.expect("must be set since handles wasn't empty");
+        let gap_limit_reached = match last_active_index {
+            Some(i) => last_index >= i.saturating_add(stop_gap as u32),
+            None => last_index + 1 >= stop_gap as u32,
+        };
+        if gap_limit_reached {
+            break;
+        }
+    }
+
+    Ok((update, last_active_index))
+}
+
+async fn fetch_txs_with_spks(
+    client: &FetchClient,
+    start_time: u64,
+    inserted_txs: &mut HashSet<BdkTxid>,
+    spks: impl IntoIterator<Item = SpkWithExpectedTxids>,
+    parallel_requests: usize,
+) -> JsResult<TxUpdate<BdkConfirmationBlockTime>> {
+    fetch_txs_with_keychain_spks(
+        client,
+        start_time,
+        inserted_txs,
+        spks.into_iter().enumerate().map(|(i, spk)| (i as u32, spk)),
+        usize::MAX,
+        parallel_requests,
+    )
+    .await
+    .map(|(update, _)| update)
+}
+
+async fn fetch_txs_with_txids(
+    client: &FetchClient,
+    start_time: u64,
+    inserted_txs: &mut HashSet<BdkTxid>,
+    txids: impl IntoIterator<Item = BdkTxid>,
+    parallel_requests: usize,
+) -> JsResult<TxUpdate<BdkConfirmationBlockTime>> {
+    let mut update = TxUpdate::<BdkConfirmationBlockTime>::default();
+    // Only fetch the transactions not inserted yet.
+    let mut txids = txids
+        .into_iter()
+        .filter(|txid| !inserted_txs.contains(txid))
+        .collect::<Vec<_>>()
+        .into_iter();
+
+    loop {
+        let handles = txids
+            .by_ref()
+            .take(parallel_requests)
+            .map(|txid| async move { client.get_tx_info(&txid).await.map(|tx| (txid, tx)) })
+            .collect::<FuturesOrdered<_>>();
+
+        if handles.is_empty() {
+            break;
+        }
+
+        for (txid, tx_info) in handles.try_collect::<Vec<_>>().await? {
+            if let Some(tx_info) = tx_info {
+                if inserted_txs.insert(txid) {
+                    update.txs.push(tx_info.to_tx().into());
+                }
+                insert_anchor_or_seen_at_from_status(&mut update, start_time, txid, tx_info.status);
+                insert_prevouts(&mut update, tx_info.vin);
+            }
+        }
+    }
+
+    Ok(update)
+}
+
+async fn fetch_txs_with_outpoints(
+    client: &FetchClient,
+    start_time: u64,
+    inserted_txs: &mut HashSet<BdkTxid>,
+    outpoints: impl IntoIterator<Item = BdkOutPoint>,
+    parallel_requests: usize,
+) -> JsResult<TxUpdate<BdkConfirmationBlockTime>> {
+    let outpoints = outpoints.into_iter().collect::<Vec<_>>();
+    let mut update = TxUpdate::<BdkConfirmationBlockTime>::default();
+
+    // Make sure the transactions of the outpoints are in the update with their status.
+    update.extend(
+        fetch_txs_with_txids(
+            client,
+            start_time,
+            inserted_txs,
+            outpoints.iter().map(|op| op.txid),
+            parallel_requests,
+        )
+        .await?,
+    );
+
+    // Spending status of the outpoints.
+    let mut outpoints = outpoints.into_iter();
+    let mut missing_txs = Vec::<BdkTxid>::with_capacity(outpoints.len());
+    loop {
+        let handles = outpoints
+            .by_ref()
+            .take(parallel_requests)
+            .map(|op| async move { client.get_output_status(&op.txid, op.vout).await })
+            .collect::<FuturesOrdered<_>>();
+
+        if handles.is_empty() {
+            break;
+        }
+
+        for op_status in handles.try_collect::<Vec<_>>().await?.into_iter().flatten() {
+            let Some(spend_txid) = op_status.txid else {
+                continue;
+            };
+            if !inserted_txs.contains(&spend_txid) {
+                missing_txs.push(spend_txid);
+            }
+            if let Some(spend_status) = op_status.status {
+                insert_anchor_or_seen_at_from_status(&mut update, start_time, spend_txid, spend_status);
+            }
+        }
+    }
+
+    update.extend(fetch_txs_with_txids(client, start_time, inserted_txs, missing_txs, parallel_requests).await?);
+    Ok(update)
+}
+
+fn insert_anchor_or_seen_at_from_status(
+    update: &mut TxUpdate<BdkConfirmationBlockTime>,
+    start_time: u64,
+    txid: BdkTxid,
+    status: TxStatus,
+) {
+    if let TxStatus {
+        block_height: Some(height),
+        block_hash: Some(hash),
+        block_time: Some(time),
+        ..
+    } = status
+    {
+        let anchor = BdkConfirmationBlockTime {
+            block_id: BlockId { height, hash },
+            confirmation_time: time,
+        };
+        update.anchors.insert((anchor, txid));
+    } else {
+        update.seen_ats.insert((txid, start_time));
+    }
+}
+
+/// Inserts the outputs spent by the inputs as floating txouts.
+fn insert_prevouts(update: &mut TxUpdate<BdkConfirmationBlockTime>, esplora_inputs: impl IntoIterator<Item = Vin>) {
+    let prevouts = esplora_inputs
+        .into_iter()
+        .filter_map(|vin| Some((vin.txid, vin.vout, vin.prevout?)));
+    for (prev_txid, prev_vout, prev_txout) in prevouts {
+        update.txouts.insert(
+            BdkOutPoint::new(prev_txid, prev_vout),
+            BdkTxOut {
+                script_pubkey: prev_txout.scriptpubkey,
+                value: BdkAmount::from_sat(prev_txout.value),
+            },
+        );
+    }
+}
@@ -1,15 +1,37 @@
+mod balance;
+mod coin_selection;
 mod descriptor;
+mod query;
+mod request_builder;
+mod rng;
+mod scan_progress;
 mod tx_builder;
+mod tx_details;
+mod tx_ordering;
 mod wallet;
+mod wallet_event;
 mod wallet_tx;
 
+pub use coin_selection::*;
 pub use descriptor::*;
+pub use query::*;
+pub use request_builder::*;
+pub(crate) use rng::JsRng;
+pub(crate) use scan_progress::ProgressCallback;
+pub use scan_progress::ScanProgress;
 pub use tx_builder::*;
+pub use tx_details::*;
+pub use tx_ordering::*;
 pub use wallet::*;
+pub use wallet_event::*;
 pub use wallet_tx::*;
 
 #[cfg(feature = "esplora")]
 mod esplora_client;
+#[cfg(feature = "esplora")]
+mod esplora_fetch;
+#[cfg(feature = "esplora")]
+mod esplora_scan;
 
 #[cfg(feature = "esplora")]
-pub use esplora_client::EsploraClient;
+pub use esplora_client::{EsploraClient, EsploraClientOptions};
@@ -0,0 +1,289 @@
+use bdk_wallet::{
+    bitcoin::{Amount as BdkAmount, Txid as BdkTxid},
+    chain::{ChainPosition as BdkChainPosition, ConfirmationBlockTime as BdkConfirmationBlockTime},
+    KeychainKind as BdkKeychainKind, LocalOutput as BdkLocalOutput, Wallet as BdkWallet, WalletTx as BdkWalletTx,
+};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::types::{Amount, KeychainKind, LocalOutput};
+
+use super::TxDetails;
+
+/// Order of the items returned by a query.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum QuerySort {
+    /// By confirmation height, unconfirmed items being the most recent (default)
+    ConfirmationHeight = "confirmation_height",
+    /// By the time the transaction was first seen in the mempool, or confirmed if never seen unconfirmed
+    FirstSeen = "first_seen",
+}
+
+/// Options of [`Wallet::query_transactions`], [`Wallet::query_unspent`] and [`Wallet::query_outputs`].
+///
+/// Filters are applied first, then the items are sorted and the page at `offset` of at most `limit`
+/// items is returned. By default, every item is returned from the most recent to the oldest.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct QueryOptions {
+    offset: u32,
+    limit: Option<u32>,
+    sort: QuerySort,
+    descending: bool,
+    keychain: Option<KeychainKind>,
+    confirmed: Option<bool>,
+    min_value: Option<Amount>,
+    from_time: Option<u64>,
+    to_time: Option<u64>,
+}
+
+#[wasm_bindgen]
+impl QueryOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        QueryOptions {
+            offset: 0,
+            limit: None,
+            sort: QuerySort::ConfirmationHeight,
+            descending: true,
+            keychain: None,
+            confirmed: None,
+            min_value: None,
+            from_time: None,
+            to_time: None,
+        }
+    }
+
+    /// Number of items to skip (default `0`)
+    #[wasm_bindgen(getter)]
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_offset(&mut self, offset: u32) {
+        self.offset = offset;
+    }
+
+    /// Maximum number of items to return, unlimited if not set
+    #[wasm_bindgen(getter)]
+    pub fn limit(&self) -> Option<u32> {
+        self.limit
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_limit(&mut self, limit: Option<u32>) {
+        self.limit = limit;
+    }
+
+    /// Order of the items
+    #[wasm_bindgen(getter)]
+    pub fn sort(&self) -> QuerySort {
+        self.sort
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_sort(&mut self, sort: QuerySort) {
+        self.sort = sort;
+    }
+
+    /// Whether the most recent items come first (default `true`)
+    #[wasm_bindgen(getter)]
+    pub fn descending(&self) -> bool {
+        self.descending
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_descending(&mut self, descending: bool) {
+        self.descending = descending;
+    }
+
+    /// Only keep outputs of this keychain, or transactions spending from or receiving to it
+    #[wasm_bindgen(getter)]
+    pub fn keychain(&self) -> Option<KeychainKind> {
+        self.keychain
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_keychain(&mut self, keychain: Option<KeychainKind>) {
+        self.keychain = keychain;
+    }
+
+    /// Only keep confirmed items if `true`, or unconfirmed ones if `false`
+    #[wasm_bindgen(getter)]
+    pub fn confirmed(&self) -> Option<bool> {
+        self.confirmed
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_confirmed(&mut self, confirmed: Option<bool>) {
+        self.confirmed = confirmed;
+    }
+
+    /// Only keep outputs of at least this value, or transactions changing the balance by at least
+    /// this amount
+    #[wasm_bindgen(getter)]
+    pub fn min_value(&self) -> Option<Amount> {
+        self.min_value
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_min_value(&mut self, min_value: Option<Amount>) {
+        self.min_value = min_value;
+    }
+
+    /// Only keep items first seen or confirmed at or after this unix timestamp
+    #[wasm_bindgen(getter)]
+    pub fn from_time(&self) -> Option<u64> {
+        self.from_time
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_from_time(&mut self, from_time: Option<u64>) {
+        self.from_time = from_time;
+    }
+
+    /// Only keep items first seen or confirmed at or before this unix timestamp
+    #[wasm_bindgen(getter)]
+    pub fn to_time(&self) -> Option<u64> {
+        self.to_time
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_to_time(&mut self, to_time: Option<u64>) {
+        self.to_time = to_time;
+    }
+}
+
+impl Default for QueryOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Position of an item in the query results, before applying the direction.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum SortKey {
+    ConfirmationHeight(BdkChainPosition<BdkConfirmationBlockTime>, BdkTxid),
+    FirstSeen(Option<u64>, BdkTxid),
+}
+
+impl QueryOptions {
+    pub(crate) fn query_txs(&self, wallet: &BdkWallet) -> Vec<TxDetails> {
+        let keychain = self.keychain.map(BdkKeychainKind::from);
+        let txs = wallet.transactions().filter(|tx| {
+            let involves_keychain = || {
+                keychain.map_or(true, |keychain| {
+                    let (sent, received) = wallet
+                        .spk_index()
+                        .sent_and_received(&tx.tx_node.tx, keychain..=keychain);
+                    sent > BdkAmount::ZERO || received > BdkAmount::ZERO
+                })
+            };
+            let has_min_value = || {
+                self.min_value.map_or(true, |min_value| {
+                    let net_value = wallet.spk_index().net_value(&tx.tx_node.tx, ..);
+                    net_value.unsigned_abs() >= min_value.into()
+                })
+            };
+            self.matches_position(wallet, tx.tx_node.txid, &tx.chain_position) && involves_keychain() && has_min_value()
+        });
+
+        self.page(wallet, txs, |tx: &BdkWalletTx| (tx.tx_node.txid, tx.chain_position))
+            .into_iter()
+            .map(|tx| TxDetails::new(wallet, tx))
+            .collect()
+    }
+
+    pub(crate) fn query_outputs(
+        &self,
+        wallet: &BdkWallet,
+        outputs: impl Iterator<Item = BdkLocalOutput>,
+    ) -> Vec<LocalOutput> {
+        let keychain = self.keychain.map(BdkKeychainKind::from);
+        let outputs = outputs.filter(|output| {
+            keychain.map_or(true, |keychain| output.keychain == keychain)
+                && self
+                    .min_value
+                    .map_or(true, |min_value| output.txout.value >= min_value.into())
+                && self.matches_position(wallet, output.outpoint.txid, &output.chain_position)
+        });
+
+        self.page(wallet, outputs, |output: &BdkLocalOutput| {
+            (output.outpoint.txid, output.chain_position)
+        })
+        .into_iter()
+        .map(Into::into)
+        .collect()
+    }
+
+    fn matches_position(
+        &self,
+        wallet: &BdkWallet,
+        txid: BdkTxid,
+        chain_position: &BdkChainPosition<BdkConfirmationBlockTime>,
+    ) -> bool {
+        if self
+            .confirmed
+            .is_some_and(|confirmed| confirmed != chain_position.is_confirmed())
+        {
+            return false;
+        }
+        if self.from_time.is_none() && self.to_time.is_none() {
+            return true;
+        }
+
+        first_seen(wallet, txid, chain_position).is_some_and(|time| {
+            self.from_time.map_or(true, |from_time| time >= from_time)
+                && self.to_time.map_or(true, |to_time| time <= to_time)
+        })
+    }
+
+    fn page<T>(
+        &self,
+        wallet: &BdkWallet,
+        items: impl Iterator<Item = T>,
+        position: impl Fn(&T) -> (BdkTxid, BdkChainPosition<BdkConfirmationBlockTime>),
+    ) -> Vec<T> {
+        let mut items: Vec<(SortKey, T)> = items
+            .map(|item| {
+                let (txid, chain_position) = position(&item);
+                let key = match self.sort {
+                    QuerySort::FirstSeen => SortKey::FirstSeen(first_seen(wallet, txid, &chain_position), txid),
+                    _ => SortKey::ConfirmationHeight(chain_position, txid),
+                };
+                (key, item)
+            })
+            .collect();
+
+        if self.descending {
+            items.sort_by(|(a, _), (b, _)| b.cmp(a));
+        } else {
+            items.sort_by(|(a, _), (b, _)| a.cmp(b));
+        }
+
+        items
+            .into_iter()
+            .skip(self.offset as usize)
+            .take(self.limit.map_or(usize::MAX, |limit| limit as usize))
+            .map(|(_, item)| item)
+            .collect()
+    }
+}
+
+// The time a transaction was first seen in the mempool, or confirmed if it never was.
+fn first_seen(
+    wallet: &BdkWallet,
+    txid: BdkTxid,
+    chain_position: &BdkChainPosition<BdkConfirmationBlockTime>,
+) -> Option<u64> {
+    let first_seen = wallet
+        .tx_graph()
+        .get_tx_node(txid)
+        .and_then(|tx_node| tx_node.first_seen);
+
+    match chain_position {
+        BdkChainPosition::Confirmed { anchor, .. } => first_seen.or(Some(anchor.confirmation_time)),
+        BdkChainPosition::Unconfirmed { first_seen: seen, .. } => seen.or(first_seen),
+    }
+}
@@ -0,0 +1,211 @@
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+use bdk_wallet::{
+    bitcoin::{OutPoint as BdkOutPoint, ScriptBuf as BdkScriptBuf, Txid as BdkTxid},
+    chain::{keychain_txout::SyncRequestBuilderExt, spk_client::SyncRequest as BdkSyncRequest},
+    KeychainKind as BdkKeychainKind, Wallet as BdkWallet,
+};
+use wasm_bindgen::{prelude::wasm_bindgen, JsError};
+use web_sys::js_sys::{Date, Function};
+
+use crate::{
+    bitcoin::ProgressCallback,
+    result::JsResult,
+    types::{FullScanRequest, KeychainKind, OutPoint, ScriptBuf, SyncRequest, Txid},
+};
+
+/// A builder of [`SyncRequest`] checking only the chosen scripts, transactions and outputs.
+///
+/// A `SyncRequestBuilder` is created by calling [`build_sync_request`] on a wallet and starts empty.
+/// Every option adds items to check, then [`build`] creates the request.
+///
+/// Each option setting method on `SyncRequestBuilder` takes and returns a new builder so you can chain calls
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct SyncRequestBuilder {
+    wallet: Rc<RefCell<BdkWallet>>,
+    spks: Vec<BdkScriptBuf>,
+    revealed_spks: bool,
+    unused_spks: bool,
+    txids: Vec<BdkTxid>,
+    outpoints: Vec<BdkOutPoint>,
+    unspent: bool,
+    unconfirmed: bool,
+    progress: Option<Function>,
+}
+
+#[wasm_bindgen]
+impl SyncRequestBuilder {
+    // Only visible to the crate to hide the use of the `Rc<RefCell<BdkWallet>>` in `Wallet::build_sync_request`.
+    pub(crate) fn new(wallet: Rc<RefCell<BdkWallet>>) -> SyncRequestBuilder {
+        SyncRequestBuilder {
+            wallet,
+            spks: vec![],
+            revealed_spks: false,
+            unused_spks: false,
+            txids: vec![],
+            outpoints: vec![],
+            unspent: false,
+            unconfirmed: false,
+            progress: None,
+        }
+    }
+
+    /// Check these scripts, which must be derived by the wallet.
+    pub fn spks(mut self, spks: Vec<ScriptBuf>) -> Self {
+        self.spks.extend(spks.into_iter().map(BdkScriptBuf::from));
+        self
+    }
+
+    /// Check every revealed script of the wallet.
+    ///
+    /// This and [`unused_spks`] are mutually exclusive, the last one set is used.
+    pub fn revealed_spks(mut self) -> Self {
+        self.revealed_spks = true;
+        self.unused_spks = false;
+        self
+    }
+
+    /// Check the revealed scripts of the wallet that never received funds.
+    ///
+    /// This and [`revealed_spks`] are mutually exclusive, the last one set is used.
+    pub fn unused_spks(mut self) -> Self {
+        self.unused_spks = true;
+        self.revealed_spks = false;
+        self
+    }
+
+    /// Check these transactions, such as our own unconfirmed sends.
+    pub fn txids(mut self, txids: Vec<Txid>) -> Self {
+        self.txids.extend(txids.into_iter().map(BdkTxid::from));
+        self
+    }
+
+    /// Check whether these outputs are spent.
+    pub fn outpoints(mut self, outpoints: Vec<OutPoint>) -> Self {
+        self.outpoints.extend(outpoints.into_iter().map(BdkOutPoint::from));
+        self
+    }
+
+    /// Check whether the unspent outputs of the wallet got spent.
+    pub fn unspent(mut self) -> Self {
+        self.unspent = true;
+        self
+    }
+
+    /// Check the unconfirmed transactions of the wallet.
+    pub fn unconfirmed(mut self) -> Self {
+        self.unconfirmed = true;
+        self
+    }
+
+    /// Function called with a [`ScanProgress`] for every item queued for checking.
+    pub fn progress(mut self, progress: Function) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Create the [`SyncRequest`].
+    ///
+    /// Returns an error if one of the scripts given to [`spks`] is not derived by the wallet.
+    pub fn build(self) -> JsResult<SyncRequest> {
+        let wallet = self.wallet.borrow();
+        let tip = wallet.local_chain().tip();
+        let index = wallet.spk_index();
+
+        let spks = self
+            .spks
+            .into_iter()
+            .map(|spk| match index.index_of_spk(spk.clone()) {
+                Some(&derivation) => Ok((derivation, spk)),
+                None => Err(JsError::new(&format!("Script {spk} is not derived by the wallet"))),
+            })
+            .collect::<JsResult<Vec<_>>>()?;
+
+        let mut builder = BdkSyncRequest::builder_at((Date::now() / 1000.0) as u64)
+            .chain_tip(tip.clone())
+            .spks_with_indexes(spks);
+        if self.revealed_spks {
+            builder = builder.revealed_spks_from_indexer(index, ..);
+        } else if self.unused_spks {
+            builder = builder.unused_spks_from_indexer(index);
+        }
+
+        let mut txids = self.txids;
+        if self.unconfirmed {
+            txids.extend(
+                wallet
+                    .transactions()
+                    .filter(|tx| !tx.chain_position.is_confirmed())
+                    .map(|tx| tx.tx_node.txid),
+            );
+        }
+        let mut outpoints = self.outpoints;
+        if self.unspent {
+            outpoints.extend(wallet.list_unspent().map(|output| output.outpoint));
+        }
+
+        builder =
+            builder
+                .txids(txids)
+                .outpoints(outpoints)
+                .expected_spk_txids(wallet.tx_graph().list_expected_spk_txids(
+                    wallet.local_chain(),
+                    tip.block_id(),
+                    index,
+                    ..,
+                ));
+
+        if let Some(progress) = self.progress {
+            builder = builder.inspect(ProgressCallback::new(progress).sync());
+        }
+        Ok(builder.build().into())
+    }
+}
+
+/// A builder of [`FullScanRequest`] with a stop gap for each keychain.
+///
+/// A `FullScanRequestBuilder` is created by calling [`build_full_scan_request`] on a wallet. The stop
+/// gap given to `EsploraClient::full_scan` applies to the keychains without their own.
+///
+/// Each option setting method on `FullScanRequestBuilder` takes and returns a new builder so you can chain calls
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct FullScanRequestBuilder {
+    wallet: Rc<RefCell<BdkWallet>>,
+    stop_gaps: BTreeMap<BdkKeychainKind, usize>,
+    progress: Option<Function>,
+}
+
+#[wasm_bindgen]
+impl FullScanRequestBuilder {
+    // Only visible to the crate to hide the use of the `Rc<RefCell<BdkWallet>>` in `Wallet::build_full_scan_request`.
+    pub(crate) fn new(wallet: Rc<RefCell<BdkWallet>>) -> FullScanRequestBuilder {
+        FullScanRequestBuilder {
+            wallet,
+            stop_gaps: BTreeMap::new(),
+            progress: None,
+        }
+    }
+
+    /// Stop scanning `keychain` after `stop_gap` consecutive scripts without transactions.
+    pub fn stop_gap(mut self, keychain: KeychainKind, stop_gap: usize) -> Self {
+        self.stop_gaps.insert(keychain.into(), stop_gap);
+        self
+    }
+
+    /// Function called with a [`ScanProgress`] for every script queued for checking.
+    pub fn progress(mut self, progress: Function) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Create the [`FullScanRequest`].
+    pub fn build(self) -> FullScanRequest {
+        let mut builder = self.wallet.borrow().start_full_scan_at((Date::now() / 1000.0) as u64);
+        if let Some(progress) = self.progress {
+            builder = builder.inspect(ProgressCallback::new(progress).full_scan());
+        }
+        FullScanRequest::with_stop_gaps(builder.build(), self.stop_gaps)
+    }
+}
@@ -0,0 +1,83 @@
+use bdk_wallet::bitcoin::key::rand::{Error as RandError, RngCore};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::js_sys::{Function, Uint8Array};
+
+use crate::{
+    types::{BdkError, BdkErrorCode},
+    utils::{js_error_message, ErrorStash},
+};
+
+/// Random bytes generated by a JS function.
+///
+/// The function is called with the number of bytes needed and returns them as a `Uint8Array`.
+///
+/// Errors of the function are kept in an [`ErrorStash`], retrieved with `take_error`. The bytes are
+/// zeroed from the first error on, so the transaction must not be used if there is one.
+#[derive(Clone)]
+pub(crate) struct JsRng {
+    random_bytes: Function,
+    error: ErrorStash,
+}
+
+impl JsRng {
+    pub(crate) fn new(random_bytes: Function) -> Self {
+        JsRng {
+            random_bytes,
+            error: ErrorStash::default(),
+        }
+    }
+
+    pub(crate) fn take_error(&self) -> Option<BdkError> {
+        self.error.take()
+    }
+
+    fn random_bytes(&self, dest: &mut [u8]) -> Result<(), BdkError> {
+        let bytes = self
+            .random_bytes
+            .call1(&JsValue::NULL, &JsValue::from(dest.len()))
+            .map_err(|e| BdkError::new(BdkErrorCode::RandomnessFailed, js_error_message(&e), ()))?;
+
+        match bytes.dyn_into::<Uint8Array>() {
+            Ok(bytes) if bytes.length() as usize == dest.len() => {
+                bytes.copy_to(dest);
+                Ok(())
+            }
+            _ => Err(BdkError::new(
+                BdkErrorCode::RandomnessFailed,
+                format!("Random bytes function must return a Uint8Array of {} bytes", dest.len()),
+                dest.len(),
+            )),
+        }
+    }
+}
+
+impl RngCore for JsRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if self.error.is_set() {
+            dest.fill(0);
+            return;
+        }
+
+        if let Err(e) = self.random_bytes(dest) {
+            self.error.set(e);
+            dest.fill(0);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), RandError> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+use bdk_wallet::{
+    bitcoin::{OutPoint as BdkOutPoint, Script, Txid as BdkTxid},
+    chain::spk_client::{SyncItem, SyncProgress},
+    KeychainKind as BdkKeychainKind,
+};
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use web_sys::js_sys::Function;
+
+use crate::types::{KeychainKind, OutPoint, Txid};
+
+/// Progress of a full scan or sync, reported for every script, txid or outpoint queued for checking.
+///
+/// An item is reported when the client takes it from the request to send its request, not when the
+/// response arrives, so the counts run up to `parallel_requests` items ahead of the completed checks.
+/// A full scan goes through the scripts of each keychain until the stop gap is reached, so its total
+/// is unknown. A sync knows every item to check upfront.
+#[wasm_bindgen]
+pub struct ScanProgress {
+    keychain: Option<BdkKeychainKind>,
+    index: Option<u32>,
+    txid: Option<BdkTxid>,
+    outpoint: Option<BdkOutPoint>,
+    spks_checked: usize,
+    txids_checked: usize,
+    outpoints_checked: usize,
+    total: Option<usize>,
+}
+
+#[wasm_bindgen]
+impl ScanProgress {
+    /// Keychain of the script queued
+    #[wasm_bindgen(getter)]
+    pub fn keychain(&self) -> Option<KeychainKind> {
+        self.keychain.map(Into::into)
+    }
+
+    /// Derivation index of the script queued
+    #[wasm_bindgen(getter)]
+    pub fn index(&self) -> Option<u32> {
+        self.index
+    }
+
+    /// Txid queued
+    #[wasm_bindgen(getter)]
+    pub fn txid(&self) -> Option<Txid> {
+        self.txid.map(Into::into)
+    }
+
+    /// Outpoint queued
+    #[wasm_bindgen(getter)]
+    pub fn outpoint(&self) -> Option<OutPoint> {
+        self.outpoint.map(Into::into)
+    }
+
+    /// Number of scripts queued so far, of the current keychain for a full scan
+    #[wasm_bindgen(getter)]
+    pub fn spks_checked(&self) -> usize {
+        self.spks_checked
+    }
+
+    /// Number of txids queued so far
+    #[wasm_bindgen(getter)]
+    pub fn txids_checked(&self) -> usize {
+        self.txids_checked
+    }
+
+    /// Number of outpoints queued so far
+    #[wasm_bindgen(getter)]
+    pub fn outpoints_checked(&self) -> usize {
+        self.outpoints_checked
+    }
+
+    /// Number of items to check in total, `undefined` for a full scan
+    #[wasm_bindgen(getter)]
+    pub fn total(&self) -> Option<usize> {
+        self.total
+    }
+}
+
+/// JS callback receiving the [`ScanProgress`] of a request from its inspect hook.
+///
+/// Errors thrown by the callback are ignored so that they don't interrupt the scan.
+pub(crate) struct ProgressCallback(Function);
+
+// SAFETY: Wasm is single-threaded; the callback is never called concurrently.
+unsafe impl Send for ProgressCallback {}
+
+impl ProgressCallback {
+    pub(crate) fn new(progress: Function) -> Self {
+        ProgressCallback(progress)
+    }
+
+    /// Inspect hook of a full scan request, counting the scripts queued per keychain.
+    pub(crate) fn full_scan(self) -> impl FnMut(BdkKeychainKind, u32, &Script) + Send + 'static {
+        let mut current: Option<BdkKeychainKind> = None;
+        let mut spks_checked = 0;
+        move |keychain, index, _spk| {
+            if current != Some(keychain) {
+                current = Some(keychain);
+                spks_checked = 0;
+            }
+            spks_checked += 1;
+            self.report(ScanProgress {
+                keychain: Some(keychain),
+                index: Some(index),
+                txid: None,
+                outpoint: None,
+                spks_checked,
+                txids_checked: 0,
+                outpoints_checked: 0,
+                total: None,
+            });
+        }
+    }
+
+    /// Inspect hook of a sync request.
+    pub(crate) fn sync(self) -> impl FnMut(SyncItem<(BdkKeychainKind, u32)>, SyncProgress) + Send + 'static {
+        move |item, progress| {
+            let mut scan_progress = ScanProgress {
+                keychain: None,
+                index: None,
+                txid: None,
+                outpoint: None,
+                spks_checked: progress.spks_consumed,
+                txids_checked: progress.txids_consumed,
+                outpoints_checked: progress.outpoints_consumed,
+                total: Some(progress.total()),
+            };
+            match item {
+                SyncItem::Spk((keychain, index), _) => {
+                    scan_progress.keychain = Some(keychain);
+                    scan_progress.index = Some(index);
+                }
+                SyncItem::Txid(txid) => scan_progress.txid = Some(txid),
+                SyncItem::OutPoint(outpoint) => scan_progress.outpoint = Some(outpoint),
+            }
+            self.report(scan_progress);
+        }
+    }
+
+    fn report(&self, progress: ScanProgress) {
+        let _ = self.0.call1(&JsValue::NULL, &progress.into());
+    }
+}
@@ -1,10 +1,39 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc, sync::Arc};
 
-use bdk_wallet::{error::CreateTxError, TxOrdering as BdkTxOrdering, Wallet as BdkWallet};
+use bdk_wallet::{
+    bitcoin::{
+        absolute::{Height, LockTime},
+        key::rand::{thread_rng, RngCore},
+        psbt::{Input as BdkPsbtInput, PsbtSighashType},
+        Amount as BdkAmount, FeeRate as BdkFeeRate, OutPoint as BdkOutPoint, Psbt as BdkPsbt,
+        ScriptBuf as BdkScriptBuf, Sequence, Transaction as BdkTransaction, Txid as BdkTxid, Weight,
+    },
+    coin_selection::{
+        CoinSelectionAlgorithm, DefaultCoinSelectionAlgorithm, LargestFirstCoinSelection, OldestFirstCoinSelection,
+        SingleRandomDraw,
+    },
+    error::{BuildFeeBumpError, CreateTxError},
+    AddForeignUtxoError, AddUtxoError, AddressInfo, KeychainKind, TxBuilder as BdkTxBuilder,
+    TxOrdering as BdkTxOrdering, Wallet as BdkWallet,
+};
+use bitcoin::script::PushBytesBuf;
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
 use serde::Serialize;
-use wasm_bindgen::prelude::wasm_bindgen;
+use serde_wasm_bindgen::from_value;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use web_sys::js_sys::Function;
 
-use crate::types::{Amount, BdkError, BdkErrorCode, FeeRate, OutPoint, Psbt, Recipient, ScriptBuf};
+use crate::{
+    bitcoin::{CoinSelection, JsCoinSelection, JsRng, JsTxOrdering, TxOrdering},
+    types::{
+        Amount, BdkError, BdkErrorCode, FeeRate, KeychainKind as JsKeychainKind, OutPoint, Psbt, PsbtInput, Recipient,
+        ScriptBuf,
+    },
+};
+
+/// Maximum size of the data pushed in an OP_RETURN output to be standard, as per Bitcoin Core's default
+/// `-datacarriersize` of 83 bytes for the whole script.
+const MAX_OP_RETURN_DATA_SIZE: usize = 80;
 
 /// A transaction builder.
 ///
@@ -14,15 +43,40 @@ use crate::types::{Amount, BdkError, BdkErrorCode, FeeRate, OutPoint, Psbt, Reci
 ///
 /// Each option setting method on `TxBuilder` takes and returns a new builder so you can chain calls
 #[wasm_bindgen]
+#[derive(Clone)]
 pub struct TxBuilder {
     wallet: Rc<RefCell<BdkWallet>>,
     recipients: Vec<Recipient>,
+    utxos: Vec<OutPoint>,
+    utxo_sequences: BTreeMap<BdkOutPoint, Sequence>,
+    foreign_utxos: Vec<ForeignUtxo>,
     unspendable: Vec<OutPoint>,
     fee_rate: FeeRate,
+    fee_absolute: Option<Amount>,
+    manually_selected_only: bool,
     drain_wallet: bool,
     drain_to: Option<ScriptBuf>,
+    data: Vec<Vec<u8>>,
     allow_dust: bool,
     ordering: TxOrdering,
+    custom_ordering: Option<(Function, Function)>,
+    seed: Option<u64>,
+    random_bytes: Option<Function>,
+    locktime: Option<LockTime>,
+    current_height: Option<u32>,
+    version: Option<i32>,
+    sequence: Option<Sequence>,
+    enable_rbf: bool,
+    external_policy_path: Option<BTreeMap<String, Vec<usize>>>,
+    internal_policy_path: Option<BTreeMap<String, Vec<usize>>>,
+    only_witness_utxo: bool,
+    include_output_redeem_witness_script: bool,
+    add_global_xpubs: bool,
+    sighash: Option<PsbtSighashType>,
+    coin_selection: CoinSelection,
+    custom_coin_selection: Option<Function>,
+    bump_fee: Option<BdkTxid>,
+    cpfp: Option<BdkTxid>,
 }
 
 #[wasm_bindgen]
@@ -32,16 +86,58 @@ impl TxBuilder {
         TxBuilder {
             wallet,
             recipients: vec![],
+            utxos: vec![],
+            utxo_sequences: BTreeMap::new(),
+            foreign_utxos: vec![],
             unspendable: vec![],
             fee_rate: FeeRate::new(1),
+            fee_absolute: None,
+            manually_selected_only: false,
             drain_wallet: false,
             allow_dust: false,
             drain_to: None,
-            ordering: BdkTxOrdering::default().into(),
+            data: vec![],
+            ordering: TxOrdering::default(),
+            custom_ordering: None,
+            seed: None,
+            random_bytes: None,
+            locktime: None,
+            current_height: None,
+            version: None,
+            sequence: None,
+            enable_rbf: false,
+            external_policy_path: None,
+            internal_policy_path: None,
+            only_witness_utxo: false,
+            include_output_redeem_witness_script: false,
+            add_global_xpubs: false,
+            sighash: None,
+            coin_selection: CoinSelection::default(),
+            custom_coin_selection: None,
+            bump_fee: None,
+            cpfp: None,
+        }
+    }
+
+    // Same as `new` but the transaction is built as a replacement of `txid`, see `Wallet::build_fee_bump`.
+    pub(crate) fn new_fee_bump(wallet: Rc<RefCell<BdkWallet>>, txid: BdkTxid) -> TxBuilder {
+        TxBuilder {
+            bump_fee: Some(txid),
+            ..TxBuilder::new(wallet)
+        }
+    }
+
+    // Same as `new` but the transaction is built as a child of `parent_txid`, see `Wallet::build_cpfp`.
+    pub(crate) fn new_cpfp(wallet: Rc<RefCell<BdkWallet>>, parent_txid: BdkTxid) -> TxBuilder {
+        TxBuilder {
+            cpfp: Some(parent_txid),
+            ..TxBuilder::new(wallet)
         }
     }
 
     /// Replace the recipients already added with a new list
+    ///
+    /// See [`Recipient::subtract_fee_from_amount`] to pay the fee from the amount of a recipient.
     pub fn set_recipients(mut self, recipients: Vec<Recipient>) -> Self {
         self.recipients = recipients;
         self
@@ -53,6 +149,101 @@ impl TxBuilder {
         self
     }
 
+    /// Add the list of outpoints to the internal list of UTXOs that **must** be spent.
+    ///
+    /// These have priority over the "unspendable" utxos, meaning that if a utxo is present both in
+    /// the "utxos" and the "unspendable" list, it will be spent.
+    ///
+    /// An outpoint unknown to the wallet makes [`finish`] fail with an `UnknownUtxo` error.
+    pub fn add_utxos(mut self, outpoints: Vec<OutPoint>) -> Self {
+        self.utxos.extend(outpoints);
+        self
+    }
+
+    /// Add a utxo to the internal list of utxos that **must** be spent
+    ///
+    /// These have priority over the "unspendable" utxos, meaning that if a utxo is present both in
+    /// the "utxos" and the "unspendable" list, it will be spent.
+    pub fn add_utxo(mut self, outpoint: OutPoint) -> Self {
+        self.utxos.push(outpoint);
+        self
+    }
+
+    /// Same as [`add_utxo`] but allows to set the nSequence value of the input.
+    ///
+    /// This overrides the sequence set with [`set_exact_sequence`] or [`enable_rbf_with_sequence`] for
+    /// this input only, so that each input can have its own. The nSequence is not checked against
+    /// the wallet's descriptors: if they contain an "older" (OP_CSV) operator, it must satisfy it
+    /// for the input to be signed. The transaction signals replaceability (BIP 125) if any of its
+    /// inputs has a nSequence lower than `0xFFFFFFFE`.
+    pub fn add_utxo_with_sequence(mut self, outpoint: OutPoint, sequence: u32) -> Self {
+        self.utxo_sequences
+            .insert(outpoint.clone().into(), Sequence::from_consensus(sequence));
+        self.utxos.push(outpoint);
+        self
+    }
+
+    /// Add a foreign UTXO i.e. a UTXO not known by this wallet.
+    ///
+    /// At a minimum to add a foreign UTXO we need:
+    ///
+    /// 1. `outpoint`: To add it to the raw transaction.
+    /// 2. `psbt_input`: To know the value.
+    /// 3. `satisfaction_weight`: To know how much weight (in weight units) the input will add to
+    ///    the transaction for fee calculation.
+    ///
+    /// The `psbt_input` must have a `witness_utxo` or a `non_witness_utxo`, otherwise [`finish`]
+    /// fails with a `MissingUtxo` error. The `non_witness_utxo` is only checked against the
+    /// `outpoint` when there is no `witness_utxo`: [`finish`] then fails with an `InvalidTxid`
+    /// error if its txid differs, or an `InvalidOutpoint` error if it has no output at that index.
+    /// If a `witness_utxo` is provided then the value of the input is taken as a given, even with
+    /// a `non_witness_utxo` -- it is up to you to check that whoever sent you the `psbt_input` was
+    /// not lying! Unless [`only_witness_utxo`] is set, any non-taproot `psbt_input` must have a
+    /// `non_witness_utxo`, otherwise [`finish`] fails with a `MissingNonWitnessUtxo` error.
+    ///
+    /// A manually added local UTXO always has precedence over a foreign UTXO.
+    ///
+    /// The input of a foreign UTXO has a sequence of `0xFFFFFFFF`, use [`add_foreign_utxo_with_sequence`]
+    /// to choose it.
+    pub fn add_foreign_utxo(self, outpoint: OutPoint, psbt_input: PsbtInput, satisfaction_weight: u64) -> Self {
+        self.add_foreign_utxo_with_sequence(
+            outpoint,
+            psbt_input,
+            satisfaction_weight,
+            Sequence::MAX.to_consensus_u32(),
+        )
+    }
+
+    /// Same as [`add_foreign_utxo`] but allows to set the nSequence value of the input.
+    ///
+    /// Unlike the sequence set with [`set_exact_sequence`] or [`enable_rbf_with_sequence`], which applies to
+    /// the inputs spending the wallet's UTXOs, this one is specific to the foreign UTXO, like the one of
+    /// [`add_utxo_with_sequence`] for a UTXO of the wallet.
+    pub fn add_foreign_utxo_with_sequence(
+        mut self,
+        outpoint: OutPoint,
+        psbt_input: PsbtInput,
+        satisfaction_weight: u64,
+        sequence: u32,
+    ) -> Self {
+        self.foreign_utxos.push(ForeignUtxo {
+            outpoint: outpoint.into(),
+            psbt_input: psbt_input.into(),
+            satisfaction_weight: Weight::from_wu(satisfaction_weight),
+            sequence: Sequence::from_consensus(sequence),
+        });
+        self
+    }
+
+    /// Only spend utxos added by [`add_utxo`].
+    ///
+    /// The wallet will **not** add additional utxos to the transaction even if they are needed to
+    /// make the transaction valid. Without any utxo added, [`finish`] fails with a `NoUtxosSelected` error.
+    pub fn manually_selected_only(mut self) -> Self {
+        self.manually_selected_only = true;
+        self
+    }
+
     /// Replace the internal list of unspendable utxos with a new list
     pub fn unspendable(mut self, unspendable: Vec<OutPoint>) -> Self {
         self.unspendable = unspendable;
@@ -77,6 +268,19 @@ impl TxBuilder {
     /// excess might not be viable.
     pub fn fee_rate(mut self, fee_rate: FeeRate) -> Self {
         self.fee_rate = fee_rate;
+        self.fee_absolute = None;
+        self
+    }
+
+    /// Set an absolute fee.
+    ///
+    /// The fee rate and the absolute fee are mutually exclusive, the last one set is used.
+    ///
+    /// Note that this is really a minimum absolute fee -- it's possible to
+    /// overshoot it slightly since adding a change output to drain the remaining
+    /// excess might not be viable.
+    pub fn fee_absolute(mut self, fee_amount: Amount) -> Self {
+        self.fee_absolute = Some(fee_amount);
         self
     }
 
@@ -86,6 +290,17 @@ impl TxBuilder {
         self
     }
 
+    /// Add data as an output, using OP_RETURN.
+    ///
+    /// The output is appended after the recipients. To be relayed by default by Bitcoin Core nodes,
+    /// the data can't exceed 80 bytes, otherwise [`finish`] fails with a `DataTooLarge` error, and a
+    /// transaction can only have one OP_RETURN output: calling `add_data` more than once makes
+    /// [`finish`] fail with a `MultipleDataOutputs` error.
+    pub fn add_data(mut self, data: Vec<u8>) -> Self {
+        self.data.push(data);
+        self
+    }
+
     /// Sets the address to *drain* excess coins to.
     ///
     /// Usually, when there are excess coins they are sent to a change address generated by the
@@ -113,6 +328,209 @@ impl TxBuilder {
     /// Choose the ordering for inputs and outputs of the transaction
     pub fn ordering(mut self, ordering: TxOrdering) -> Self {
         self.ordering = ordering;
+        self.custom_ordering = None;
+        self
+    }
+
+    /// Order the inputs and outputs of the transaction with JS comparators.
+    ///
+    /// `input_sort` is called with two `TxIn` and `output_sort` with two `TxOut`, each returns a
+    /// negative number, zero or a positive number like the comparators of `Array.prototype.sort`.
+    ///
+    /// An error thrown by a comparator, or a value which is not a number, fails [`finish`] with an
+    /// `OrderingFailed` error. The wallet is borrowed while building the transaction, so the
+    /// comparators must not call it.
+    pub fn ordering_custom(mut self, input_sort: Function, output_sort: Function) -> Self {
+        self.custom_ordering = Some((input_sort, output_sort));
+        self
+    }
+
+    /// Use a specific nLockTime while creating the transaction.
+    ///
+    /// Values below 500000000 are interpreted as a block height, others as a UNIX timestamp. This can
+    /// cause conflicts if the wallet's descriptors contain an "after" (OP_CLTV) operator, in which case
+    /// [`finish`] fails with a `LockTime` error.
+    pub fn nlocktime(mut self, locktime: u32) -> Self {
+        self.locktime = Some(LockTime::from_consensus(locktime));
+        self
+    }
+
+    /// Set the current blockchain height.
+    ///
+    /// This will be used to:
+    /// 1. Set the nLockTime for preventing fee sniping. **Note**: This will be ignored if you
+    ///    manually specify a nlocktime using [`nlocktime`].
+    /// 2. Decide whether coinbase outputs are mature or not. If the coinbase outputs are not mature
+    ///    at spending height, which is `current_height` + 1, we ignore them in the coin selection.
+    ///    If you want to create a transaction that spends immature coinbase inputs, manually add
+    ///    them using [`add_utxos`].
+    ///
+    /// In both cases, if you don't provide a current height, we use the last sync height. A height
+    /// of 500000000 or more makes [`finish`] fail with an `InvalidHeight` error.
+    pub fn current_height(mut self, height: u32) -> Self {
+        self.current_height = Some(height);
+        self
+    }
+
+    /// Build a transaction with a specific version.
+    ///
+    /// The `version` should always be greater than `0` and greater than `1` if the wallet's
+    /// descriptors contain an "older" (OP_CSV) operator, otherwise [`finish`] fails with a
+    /// `Version0` or `Version1Csv` error. Default is `2`.
+    pub fn version(mut self, version: i32) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Signal replaceability (BIP 125) with the default nSequence of `0xFFFFFFFD`.
+    ///
+    /// Transactions signal replaceability unless [`disable_rbf`] or [`set_exact_sequence`] is used.
+    pub fn enable_rbf(self) -> Self {
+        self.enable_rbf_with_sequence(Sequence::ENABLE_RBF_NO_LOCKTIME.to_consensus_u32())
+    }
+
+    /// Signal replaceability (BIP 125) with a specific nSequence.
+    ///
+    /// The nSequence must be lower than `0xFFFFFFFE`, otherwise [`finish`] fails with a `RbfSequence`
+    /// error. If the wallet's descriptors contain an "older" (OP_CSV) operator, it must also satisfy
+    /// it, otherwise [`finish`] fails with a `RbfSequenceCsv` error.
+    pub fn enable_rbf_with_sequence(mut self, nsequence: u32) -> Self {
+        self.sequence = Some(Sequence::from_consensus(nsequence));
+        self.enable_rbf = true;
+        self
+    }
+
+    /// Do not signal replaceability (BIP 125).
+    ///
+    /// The inputs use a nSequence of `0xFFFFFFFE`, which keeps the nLockTime enforced.
+    pub fn disable_rbf(self) -> Self {
+        self.set_exact_sequence(Sequence::ENABLE_LOCKTIME_NO_RBF.to_consensus_u32())
+    }
+
+    /// Set an exact nSequence value for the inputs spending the wallet's UTXOs.
+    ///
+    /// Use [`add_utxo_with_sequence`] to give a different nSequence to a specific input.
+    ///
+    /// This can cause conflicts if the wallet's descriptors contain an "older" (OP_CSV) operator
+    /// and the given `nsequence` is lower than the CSV value, in which case [`finish`] fails with a
+    /// `RbfSequenceCsv` error.
+    pub fn set_exact_sequence(mut self, nsequence: u32) -> Self {
+        self.sequence = Some(Sequence::from_consensus(nsequence));
+        self.enable_rbf = false;
+        self
+    }
+
+    /// Set the policy path to use while creating the transaction for a given keychain.
+    ///
+    /// This method accepts a map where the key is the policy node id (see [`Policy::id`]) and the
+    /// value is the list of the indexes of the items that are intended to be satisfied from the
+    /// policy node (see [`Policy::children`]), for instance `{ "aabbccdd": [0, 1] }`.
+    ///
+    /// A path is required when the wallet's descriptor has several spending branches (see
+    /// [`Policy::requires_path`]), otherwise [`finish`] fails with a `SpendingPolicyRequired` error.
+    pub fn policy_path(
+        mut self,
+        #[wasm_bindgen(unchecked_param_type = "Record<string, number[]> | Map<string, number[]>")] policy_path: JsValue,
+        keychain: JsKeychainKind,
+    ) -> Result<Self, BdkError> {
+        let policy_path = from_value(policy_path).map_err(|e| {
+            BdkError::new(
+                BdkErrorCode::Policy,
+                format!("Policy path must map node ids to lists of indexes: {e}"),
+                (),
+            )
+        })?;
+
+        match keychain {
+            JsKeychainKind::Internal => self.internal_policy_path = Some(policy_path),
+            _ => self.external_policy_path = Some(policy_path),
+        }
+        Ok(self)
+    }
+
+    /// Only Fill-in the `witness_utxo` field of the PSBT inputs when spending from SegWit descriptors.
+    ///
+    /// This reduces the size of the PSBT, but some signers might reject them due to the lack of
+    /// the `non_witness_utxo`.
+    pub fn only_witness_utxo(mut self) -> Self {
+        self.only_witness_utxo = true;
+        self
+    }
+
+    /// Fill-in the `redeem_script` and `witness_script` fields of the PSBT outputs.
+    ///
+    /// This is useful for signers which always require it, like ColdCard hardware wallets.
+    pub fn include_output_redeem_witness_script(mut self) -> Self {
+        self.include_output_redeem_witness_script = true;
+        self
+    }
+
+    /// Fill-in the `PSBT_GLOBAL_XPUB` field with the extended keys contained in both the external
+    /// and internal descriptors.
+    ///
+    /// This is useful for offline signers that take part to a multisig. Some hardware wallets like
+    /// BitBox and ColdCard are known to require this. Every extended key must either be a master key
+    /// or have an explicit origin, otherwise [`finish`] fails with a `MissingKeyOrigin` error.
+    pub fn add_global_xpubs(mut self) -> Self {
+        self.add_global_xpubs = true;
+        self
+    }
+
+    /// Sign with a specific sighash type, set on every PSBT input.
+    ///
+    /// The value is the raw PSBT sighash type: `0x01` (ALL), `0x02` (NONE), `0x03` (SINGLE), optionally
+    /// combined with `0x80` (ANYONECANPAY), or `0x00` (DEFAULT) for taproot inputs.
+    ///
+    /// **Use this option very carefully**
+    pub fn sighash(mut self, sighash: u32) -> Self {
+        self.sighash = Some(PsbtSighashType::from_u32(sighash));
+        self
+    }
+
+    /// Choose the coin selection algorithm.
+    ///
+    /// Default is [`CoinSelection::BranchAndBound`].
+    pub fn coin_selection(mut self, coin_selection: CoinSelection) -> Self {
+        self.coin_selection = coin_selection;
+        self.custom_coin_selection = None;
+        self
+    }
+
+    /// Use a JS function as coin selection algorithm.
+    ///
+    /// The function is called with the candidate `LocalOutput[]`, the target `Amount` and the
+    /// `FeeRate` of the transaction, and must return the `OutPoint[]` to spend. The target accounts
+    /// for the recipients, the fees already known and the utxos that **must** be spent, which are
+    /// always selected and never part of the candidates. The fee of spending the returned outpoints
+    /// is added to the target: the selection fails with an `InsufficientFunds` error if it doesn't
+    /// cover it.
+    ///
+    /// An error thrown by the function fails [`finish`] with a `CoinSelectionFailed` error. The
+    /// wallet is borrowed while building the transaction, so the function must not call it.
+    pub fn coin_selection_custom(mut self, selector: Function) -> Self {
+        self.custom_coin_selection = Some(selector);
+        self
+    }
+
+    /// Seed the randomness used to build the transaction.
+    ///
+    /// The randomness shuffles the inputs and outputs with [`TxOrdering::Shuffle`] and picks the
+    /// utxos of the random coin selections. Building the same transaction from the same wallet state
+    /// and seed gives the same PSBT. By default, a random seed is used for every transaction.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self.random_bytes = None;
+        self
+    }
+
+    /// Use a JS function as source of randomness to build the transaction, see [`seed`].
+    ///
+    /// The function is called with the number of bytes needed and must return a `Uint8Array` of
+    /// that length. An error thrown by the function, or bytes of another length, fail [`finish`] and
+    /// [`preview`] with a `RandomnessFailed` error. The transaction is still built to completion with
+    /// zeros in place of the missing bytes, but it is discarded: no PSBT built from them is returned.
+    pub fn random_bytes(mut self, random_bytes: Function) -> Self {
+        self.random_bytes = Some(random_bytes);
         self
     }
 
@@ -120,16 +538,275 @@ impl TxBuilder {
     ///
     /// Returns a new [`Psbt`] per [`BIP174`].
     pub fn finish(self) -> Result<Psbt, BdkError> {
-        let mut wallet = self.wallet.borrow_mut();
-        let mut builder = wallet.build_tx();
+        let wallet = self.wallet.clone();
+        let mut wallet = wallet.borrow_mut();
+
+        let psbt = self.create_tx(&mut wallet)?;
+        Ok(psbt.into())
+    }
+
+    /// Preview the transaction without changing the wallet.
+    ///
+    /// Unlike [`finish`], no change address is revealed or marked as used, and nothing is staged.
+    /// The preview holds the fee, the estimated size, the selected inputs and the change amount of
+    /// the transaction that [`finish`] would build, or the error it would fail with.
+    pub fn preview(self) -> TxPreview {
+        let wallet = self.wallet.clone();
+        let mut wallet = wallet.borrow_mut();
+
+        let (psbt, drain_script) = match self.clone().create_dry_run(&mut wallet) {
+            Ok(result) => result,
+            Err(e) => return e.into(),
+        };
+        let weight = match self.estimate_weight(&wallet, &psbt) {
+            Ok(weight) => weight,
+            Err(e) => return e.into(),
+        };
+        let fee = match psbt.fee() {
+            Ok(fee) => fee,
+            Err(e) => return BdkError::new(BdkErrorCode::Psbt, e.to_string(), ()).into(),
+        };
+
+        TxPreview {
+            fee: Some(fee.into()),
+            fee_rate: Some((fee / weight).into()),
+            vsize: Some(weight.to_vbytes_ceil()),
+            inputs: psbt
+                .unsigned_tx
+                .input
+                .iter()
+                .map(|txin| txin.previous_output.into())
+                .collect(),
+            change_amount: psbt
+                .unsigned_tx
+                .output
+                .iter()
+                .find(|txout| txout.script_pubkey == drain_script)
+                .map(|txout| txout.value.into()),
+            error: None,
+        }
+    }
+}
+
+impl TxBuilder {
+    fn create_tx(self, wallet: &mut BdkWallet) -> Result<BdkPsbt, BdkError> {
+        if (self.bump_fee.is_some() || self.cpfp.is_some()) && self.recipients.iter().any(Recipient::subtracts_fee) {
+            return Err(BdkError::new(
+                BdkErrorCode::SubtractFeeUnsupported,
+                "Cannot subtract the fee from a recipient when bumping the fee or with CPFP",
+                (),
+            ));
+        }
 
+        match self.cpfp {
+            Some(parent_txid) => self.create_cpfp(wallet, parent_txid),
+            None => self.create(wallet),
+        }
+    }
+
+    // Builds the transaction without changing the wallet, returning it along with its drain script.
+    pub(crate) fn create_dry_run(mut self, wallet: &mut BdkWallet) -> Result<(BdkPsbt, BdkScriptBuf), BdkError> {
+        // Without recipients, the wallet requires a drain script that the change address would otherwise provide.
+        if self.drain_to.is_none()
+            && self.recipients.is_empty()
+            && self.data.is_empty()
+            && self.bump_fee.is_none()
+            && self.cpfp.is_none()
+        {
+            return Err(CreateTxError::NoRecipients.into());
+        }
+
+        // The next change address is peeked and used as drain script, so that the wallet doesn't reveal it.
+        let drain_script = match &self.drain_to {
+            Some(script_pubkey) => script_pubkey.clone().into(),
+            None => Self::peek_change(wallet).script_pubkey(),
+        };
+        self.drain_to = Some(drain_script.clone().into());
+
+        let psbt = self.create_tx(wallet)?;
+        Ok((psbt, drain_script))
+    }
+
+    fn create(self, wallet: &mut BdkWallet) -> Result<BdkPsbt, BdkError> {
+        if self.recipients.iter().any(Recipient::subtracts_fee) {
+            return self.create_subtracting_fee(wallet);
+        }
+        self.build(wallet)
+    }
+
+    fn build(self, wallet: &mut BdkWallet) -> Result<BdkPsbt, BdkError> {
+        let builder = match self.bump_fee {
+            Some(txid) => wallet.build_fee_bump(txid)?,
+            None => wallet.build_tx(),
+        };
+
+        let custom_ordering = self
+            .custom_ordering
+            .clone()
+            .map(|(input_sort, output_sort)| JsTxOrdering::new(input_sort, output_sort));
+        let ordering = match &custom_ordering {
+            Some(custom_ordering) => custom_ordering.clone().into(),
+            None => self.ordering.into(),
+        };
+
+        let js_rng = self.random_bytes.clone().map(JsRng::new);
+        let mut rng: Box<dyn RngCore> = match (&js_rng, self.seed) {
+            (Some(js_rng), _) => Box::new(js_rng.clone()),
+            (None, Some(seed)) => Box::new(ChaCha20Rng::seed_from_u64(seed)),
+            (None, None) => Box::new(thread_rng()),
+        };
+
+        let coin_selection = self.custom_coin_selection.clone().map(JsCoinSelection::new);
+        let result = match (&coin_selection, self.coin_selection) {
+            (Some(coin_selection), _) => {
+                self.configure(builder.coin_selection(coin_selection.clone()), ordering, &mut rng)
+            }
+            (None, CoinSelection::BranchAndBound) => self.configure(
+                builder.coin_selection(DefaultCoinSelectionAlgorithm::default()),
+                ordering,
+                &mut rng,
+            ),
+            (None, CoinSelection::LargestFirst) => {
+                self.configure(builder.coin_selection(LargestFirstCoinSelection), ordering, &mut rng)
+            }
+            (None, CoinSelection::OldestFirst) => {
+                self.configure(builder.coin_selection(OldestFirstCoinSelection), ordering, &mut rng)
+            }
+            (None, CoinSelection::SingleRandomDraw) => {
+                self.configure(builder.coin_selection(SingleRandomDraw), ordering, &mut rng)
+            }
+        };
+
+        // An error of a JS callback either caused the failure or makes the transaction unreliable.
+        let callback_error = coin_selection
+            .and_then(|coin_selection| coin_selection.take_error())
+            .or_else(|| custom_ordering.and_then(|ordering| ordering.take_error()))
+            .or_else(|| js_rng.and_then(|js_rng| js_rng.take_error()));
+        match callback_error {
+            Some(e) => Err(e),
+            None => result,
+        }
+    }
+
+    fn configure<Cs: CoinSelectionAlgorithm>(
+        self,
+        mut builder: BdkTxBuilder<'_, Cs>,
+        ordering: BdkTxOrdering,
+        rng: &mut impl RngCore,
+    ) -> Result<BdkPsbt, BdkError> {
         builder
-            .ordering(self.ordering.into())
-            .set_recipients(self.recipients.into_iter().map(Into::into).collect())
+            .ordering(ordering)
             .unspendable(self.unspendable.into_iter().map(Into::into).collect())
             .fee_rate(self.fee_rate.into())
             .allow_dust(self.allow_dust);
 
+        if let Some(fee_amount) = self.fee_absolute {
+            builder.fee_absolute(fee_amount.into());
+        }
+
+        if let Some(policy_path) = self.external_policy_path {
+            builder.policy_path(policy_path, KeychainKind::External);
+        }
+
+        if let Some(policy_path) = self.internal_policy_path {
+            builder.policy_path(policy_path, KeychainKind::Internal);
+        }
+
+        if let Some(locktime) = self.locktime {
+            builder.nlocktime(locktime);
+        }
+
+        if let Some(height) = self.current_height {
+            if Height::from_consensus(height).is_err() {
+                return Err(BdkError::new(
+                    BdkErrorCode::InvalidHeight,
+                    format!("Invalid block height: {height}"),
+                    height,
+                ));
+            }
+            builder.current_height(height);
+        }
+
+        if let Some(version) = self.version {
+            builder.version(version);
+        }
+
+        if let Some(sequence) = self.sequence {
+            if self.enable_rbf && !sequence.is_rbf() {
+                return Err(BdkError::new(
+                    BdkErrorCode::RbfSequence,
+                    format!("Cannot enable RBF with a nSequence >= 0xFFFFFFFE: {sequence:#x}"),
+                    sequence.to_consensus_u32(),
+                ));
+            }
+            builder.set_exact_sequence(sequence);
+        }
+
+        // Recipients are appended so that the outputs of a transaction being fee bumped are kept.
+        for (script_pubkey, amount) in self.recipients.into_iter().map(Into::into) {
+            builder.add_recipient(script_pubkey, amount);
+        }
+
+        if self.data.len() > 1 {
+            return Err(BdkError::new(
+                BdkErrorCode::MultipleDataOutputs,
+                format!(
+                    "Data was added {} times, a standard transaction has at most one OP_RETURN output",
+                    self.data.len()
+                ),
+                self.data.len(),
+            ));
+        }
+
+        for data in self.data {
+            if data.len() > MAX_OP_RETURN_DATA_SIZE {
+                return Err(BdkError::new(
+                    BdkErrorCode::DataTooLarge,
+                    format!(
+                        "OP_RETURN data of {} bytes exceeds the standard limit of {MAX_OP_RETURN_DATA_SIZE} bytes",
+                        data.len()
+                    ),
+                    MAX_OP_RETURN_DATA_SIZE,
+                ));
+            }
+            let data = PushBytesBuf::try_from(data).expect("data size is checked above");
+            builder.add_data(&data);
+        }
+
+        if !self.utxos.is_empty() {
+            let utxos: Vec<BdkOutPoint> = self.utxos.into_iter().map(Into::into).collect();
+            builder.add_utxos(&utxos)?;
+        }
+
+        for foreign_utxo in self.foreign_utxos {
+            builder.add_foreign_utxo_with_sequence(
+                foreign_utxo.outpoint,
+                foreign_utxo.psbt_input,
+                foreign_utxo.satisfaction_weight,
+                foreign_utxo.sequence,
+            )?;
+        }
+
+        if self.manually_selected_only {
+            builder.manually_selected_only();
+        }
+
+        if self.only_witness_utxo {
+            builder.only_witness_utxo();
+        }
+
+        if self.include_output_redeem_witness_script {
+            builder.include_output_redeem_witness_script();
+        }
+
+        if self.add_global_xpubs {
+            builder.add_global_xpubs();
+        }
+
+        if let Some(sighash) = self.sighash {
+            builder.sighash(sighash);
+        }
+
         if self.drain_wallet {
             builder.drain_wallet();
         }
@@ -138,37 +815,286 @@ impl TxBuilder {
             builder.drain_to(drain_recipient.into());
         }
 
-        let psbt = builder.finish()?;
-        Ok(psbt.into())
+        let mut psbt = builder.finish_with_aux_rand(rng)?;
+        for txin in &mut psbt.unsigned_tx.input {
+            if let Some(&sequence) = self.utxo_sequences.get(&txin.previous_output) {
+                txin.sequence = sequence;
+            }
+        }
+        Ok(psbt)
+    }
+
+    // The child transaction is first built at the target fee rate, then rebuilt with an absolute fee covering the
+    // missing fee of the parent until the package reaches the target fee rate. A higher fee can make the coin selection
+    // add inputs, so the weight of the child is estimated again after each build. The absolute fee only grows, so this
+    // ends once the fee is enough or the wallet can't pay it.
+    fn create_cpfp(mut self, wallet: &mut BdkWallet, parent_txid: BdkTxid) -> Result<BdkPsbt, BdkError> {
+        let parent = CpfpParent::new(wallet, parent_txid)?;
+        let fee_rate = BdkFeeRate::from(self.fee_rate);
+
+        self.utxos.extend(parent.outpoints.into_iter().map(Into::into));
+        let change = self.reserve_change(wallet);
+
+        self.fee_absolute = None;
+        let mut psbt = self.clone().create(wallet)?;
+        loop {
+            let child_weight = self.estimate_weight(wallet, &psbt)?;
+            let package_fee = fee_rate * (parent.tx.weight() + child_weight);
+            let needed = package_fee
+                .checked_sub(parent.fee)
+                .unwrap_or_default()
+                .max(fee_rate * child_weight);
+
+            let fee = psbt
+                .fee()
+                .map_err(|e| BdkError::new(BdkErrorCode::Psbt, e.to_string(), ()))?;
+            if fee >= needed {
+                break;
+            }
+
+            self.fee_absolute = Some(needed.into());
+            psbt = self.clone().create(wallet)?;
+        }
+        Self::use_change(wallet, &psbt, change);
+
+        Ok(psbt)
+    }
+
+    // Subtracting the fee from the recipients changes neither the inputs nor the change, so the transaction is first
+    // built without fee to select them, then rebuilt from the same inputs with the fee taken from the recipients.
+    fn create_subtracting_fee(mut self, wallet: &mut BdkWallet) -> Result<BdkPsbt, BdkError> {
+        let change = self.reserve_change(wallet);
+
+        let mut unfunded = self.clone();
+        unfunded.fee_absolute = Some(BdkAmount::ZERO.into());
+        let psbt = unfunded.build(wallet)?;
+
+        let fee_amount = match self.fee_absolute {
+            Some(fee_amount) => fee_amount.into(),
+            None => BdkFeeRate::from(self.fee_rate) * self.estimate_weight(wallet, &psbt)?,
+        };
+
+        let payers: Vec<usize> = (0..self.recipients.len())
+            .filter(|&index| self.recipients[index].subtracts_fee())
+            .collect();
+        let share = fee_amount / payers.len() as u64;
+        let remainder = fee_amount - share * payers.len() as u64;
+        for (position, &index) in payers.iter().enumerate() {
+            let recipient = &mut self.recipients[index];
+            let fee_share = if position == 0 { share + remainder } else { share };
+            let amount = BdkAmount::from(recipient.amount())
+                .checked_sub(fee_share)
+                .ok_or_else(|| {
+                    BdkError::new(
+                        BdkErrorCode::InsufficientFunds,
+                        format!("Recipient at index {index} cannot pay its share of the fee: {fee_share}"),
+                        InsufficientFunds {
+                            needed: fee_share.into(),
+                            available: recipient.amount(),
+                        },
+                    )
+                })?;
+            recipient.set_amount(amount);
+        }
+
+        let foreign_outpoints: Vec<BdkOutPoint> = self.foreign_utxos.iter().map(|utxo| utxo.outpoint).collect();
+        self.utxos = psbt
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|txin| txin.previous_output)
+            .filter(|outpoint| !foreign_outpoints.contains(outpoint))
+            .map(Into::into)
+            .collect();
+        self.manually_selected_only = true;
+        self.fee_absolute = Some(fee_amount.into());
+
+        let psbt = self.build(wallet)?;
+        Self::use_change(wallet, &psbt, change);
+
+        Ok(psbt)
+    }
+
+    // Weight of the transaction once signed, as estimated by the wallet during coin selection. Like the wallet, the
+    // segwit marker and flag are always counted.
+    fn estimate_weight(&self, wallet: &BdkWallet, psbt: &BdkPsbt) -> Result<Weight, BdkError> {
+        let mut weight = psbt.unsigned_tx.weight() + Weight::from_wu(2);
+        for txin in &psbt.unsigned_tx.input {
+            let satisfaction_weight = match wallet.get_utxo(txin.previous_output) {
+                Some(utxo) => wallet
+                    .public_descriptor(utxo.keychain)
+                    .max_weight_to_satisfy()
+                    .map_err(|e| BdkError::new(BdkErrorCode::Descriptor, e.to_string(), ()))?,
+                None => self
+                    .foreign_utxos
+                    .iter()
+                    .find(|utxo| utxo.outpoint == txin.previous_output)
+                    .map(|utxo| utxo.satisfaction_weight)
+                    .unwrap_or(Weight::ZERO),
+            };
+            // The witness length prefix is not part of the unsigned transaction.
+            weight += Weight::from_wu(1) + satisfaction_weight;
+        }
+        Ok(weight)
+    }
+
+    // Uses the next unused change address as drain script, so that building the transaction several times doesn't
+    // use several change addresses. The address is only peeked, `use_change` reveals it once the build succeeded.
+    // Returns its index if it wasn't set already.
+    fn reserve_change(&mut self, wallet: &BdkWallet) -> Option<u32> {
+        if self.drain_to.is_some() {
+            return None;
+        }
+        let change = Self::peek_change(wallet);
+        self.drain_to = Some(change.script_pubkey().into());
+        Some(change.index)
+    }
+
+    // The first unused change address, or the next one to reveal, without revealing it.
+    fn peek_change(wallet: &BdkWallet) -> AddressInfo {
+        wallet
+            .list_unused_addresses(KeychainKind::Internal)
+            .next()
+            .unwrap_or_else(|| {
+                let index = wallet
+                    .derivation_index(KeychainKind::Internal)
+                    .map_or(0, |index| index + 1);
+                wallet.peek_address(KeychainKind::Internal, index)
+            })
+    }
+
+    // Reveals and marks as used the change address reserved by `reserve_change`, if the transaction pays to it.
+    fn use_change(wallet: &mut BdkWallet, psbt: &BdkPsbt, change: Option<u32>) {
+        if let Some(index) = change {
+            let script_pubkey = wallet.peek_address(KeychainKind::Internal, index).script_pubkey();
+            if psbt
+                .unsigned_tx
+                .output
+                .iter()
+                .any(|txout| txout.script_pubkey == script_pubkey)
+            {
+                let _ = wallet.reveal_addresses_to(KeychainKind::Internal, index);
+                wallet.mark_used(KeychainKind::Internal, index);
+            }
+        }
     }
 }
 
-/// Ordering of the transaction's inputs and outputs
-#[derive(Clone, Default)]
-#[wasm_bindgen]
-pub enum TxOrdering {
-    /// Randomized (default)
-    #[default]
-    Shuffle,
-    /// Unchanged
-    Untouched,
+/// A UTXO not owned by the wallet, added with [`TxBuilder::add_foreign_utxo`].
+#[derive(Clone)]
+struct ForeignUtxo {
+    outpoint: BdkOutPoint,
+    psbt_input: BdkPsbtInput,
+    satisfaction_weight: Weight,
+    sequence: Sequence,
+}
+
+/// An unconfirmed wallet transaction to accelerate with a child paying for it.
+pub(crate) struct CpfpParent {
+    tx: Arc<BdkTransaction>,
+    fee: BdkAmount,
+    outpoints: Vec<BdkOutPoint>,
 }
 
-impl From<BdkTxOrdering> for TxOrdering {
-    fn from(ordering: BdkTxOrdering) -> Self {
-        match ordering {
-            BdkTxOrdering::Shuffle => TxOrdering::Shuffle,
-            BdkTxOrdering::Untouched => TxOrdering::Untouched,
-            _ => panic!("Unsupported ordering"),
+impl CpfpParent {
+    pub(crate) fn new(wallet: &BdkWallet, txid: BdkTxid) -> Result<Self, BdkError> {
+        let wallet_tx = wallet.get_tx(txid).ok_or_else(|| {
+            BdkError::new(
+                BdkErrorCode::TransactionNotFound,
+                format!("Transaction not found in the internal database with txid: {txid}"),
+                txid,
+            )
+        })?;
+
+        if wallet_tx.chain_position.is_confirmed() {
+            return Err(BdkError::new(
+                BdkErrorCode::TransactionConfirmed,
+                format!("Transaction already confirmed with txid: {txid}"),
+                txid,
+            ));
         }
+
+        let tx = wallet_tx.tx_node.tx.clone();
+        let fee = wallet
+            .calculate_fee(&tx)
+            .map_err(|e| BdkError::new(BdkErrorCode::FeeRateUnavailable, e.to_string(), ()))?;
+
+        let outpoints: Vec<BdkOutPoint> = wallet
+            .list_unspent()
+            .filter(|utxo| utxo.outpoint.txid == txid)
+            .map(|utxo| utxo.outpoint)
+            .collect();
+        if outpoints.is_empty() {
+            return Err(BdkError::new(
+                BdkErrorCode::NoSpendableOutputs,
+                format!("No spendable output found in transaction with txid: {txid}"),
+                txid,
+            ));
+        }
+
+        Ok(CpfpParent { tx, fee, outpoints })
     }
 }
 
-impl From<TxOrdering> for BdkTxOrdering {
-    fn from(ordering: TxOrdering) -> Self {
-        match ordering {
-            TxOrdering::Shuffle => BdkTxOrdering::Shuffle,
-            TxOrdering::Untouched => BdkTxOrdering::Untouched,
+/// Preview of a transaction, built with [`TxBuilder::preview`].
+#[wasm_bindgen]
+pub struct TxPreview {
+    fee: Option<Amount>,
+    fee_rate: Option<FeeRate>,
+    vsize: Option<u64>,
+    inputs: Vec<OutPoint>,
+    change_amount: Option<Amount>,
+    error: Option<BdkError>,
+}
+
+#[wasm_bindgen]
+impl TxPreview {
+    /// Fee of the transaction
+    #[wasm_bindgen(getter)]
+    pub fn fee(&self) -> Option<Amount> {
+        self.fee
+    }
+
+    /// Fee rate of the transaction, based on its estimated size once signed
+    #[wasm_bindgen(getter)]
+    pub fn fee_rate(&self) -> Option<FeeRate> {
+        self.fee_rate
+    }
+
+    /// Estimated virtual size of the transaction once signed
+    #[wasm_bindgen(getter)]
+    pub fn vsize(&self) -> Option<u64> {
+        self.vsize
+    }
+
+    /// Outpoints spent by the transaction
+    #[wasm_bindgen(getter)]
+    pub fn inputs(&self) -> Vec<OutPoint> {
+        self.inputs.clone()
+    }
+
+    /// Amount of the change output, if the transaction has one
+    #[wasm_bindgen(getter)]
+    pub fn change_amount(&self) -> Option<Amount> {
+        self.change_amount
+    }
+
+    /// Error the transaction cannot be built with
+    #[wasm_bindgen(getter)]
+    pub fn error(&self) -> Option<BdkError> {
+        self.error.clone()
+    }
+}
+
+impl From<BdkError> for TxPreview {
+    fn from(error: BdkError) -> Self {
+        TxPreview {
+            fee: None,
+            fee_rate: None,
+            vsize: None,
+            inputs: vec![],
+            change_amount: None,
+            error: Some(error),
         }
     }
 }
@@ -219,3 +1145,38 @@ impl From<CreateTxError> for BdkError {
         }
     }
 }
+
+impl From<AddUtxoError> for BdkError {
+    fn from(e: AddUtxoError) -> Self {
+        match &e {
+            AddUtxoError::UnknownUtxo(outpoint) => BdkError::new(BdkErrorCode::UnknownUtxo, e.to_string(), outpoint),
+        }
+    }
+}
+
+impl From<AddForeignUtxoError> for BdkError {
+    fn from(e: AddForeignUtxoError) -> Self {
+        use AddForeignUtxoError::*;
+        match &e {
+            InvalidTxid { foreign_utxo, .. } => BdkError::new(BdkErrorCode::InvalidTxid, e.to_string(), foreign_utxo),
+            InvalidOutpoint(outpoint) => BdkError::new(BdkErrorCode::InvalidOutpoint, e.to_string(), outpoint),
+            MissingUtxo => BdkError::new(BdkErrorCode::MissingUtxo, e.to_string(), ()),
+        }
+    }
+}
+
+impl From<BuildFeeBumpError> for BdkError {
+    fn from(e: BuildFeeBumpError) -> Self {
+        use BuildFeeBumpError::*;
+        match &e {
+            UnknownUtxo(outpoint) => BdkError::new(BdkErrorCode::UnknownUtxo, e.to_string(), outpoint),
+            TransactionNotFound(txid) => BdkError::new(BdkErrorCode::TransactionNotFound, e.to_string(), txid),
+            TransactionConfirmed(txid) => BdkError::new(BdkErrorCode::TransactionConfirmed, e.to_string(), txid),
+            IrreplaceableTransaction(txid) => {
+                BdkError::new(BdkErrorCode::IrreplaceableTransaction, e.to_string(), txid)
+            }
+            FeeRateUnavailable => BdkError::new(BdkErrorCode::FeeRateUnavailable, e.to_string(), ()),
+            InvalidOutputIndex(outpoint) => BdkError::new(BdkErrorCode::InvalidOutputIndex, e.to_string(), outpoint),
+        }
+    }
+}
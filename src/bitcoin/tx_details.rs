@@ -0,0 +1,183 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use bdk_wallet::{
+    bitcoin::{
+        Address as BdkAddress, Amount as BdkAmount, FeeRate as BdkFeeRate, ScriptBuf as BdkScriptBuf,
+        Transaction as BdkTransaction, Txid as BdkTxid,
+    },
+    chain::{ChainPosition as BdkChainPosition, ConfirmationBlockTime as BdkConfirmationBlockTime},
+    AddressInfo as BdkAddressInfo, KeychainKind as BdkKeychainKind, Wallet as BdkWallet, WalletTx as BdkWalletTx,
+};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::types::{AddressInfo, Amount, ChainPosition, FeeRate, Transaction, Txid};
+
+/// Number of confirmations of a transaction at `chain_position` when the tip is at `tip_height`.
+///
+/// A transaction anchored in the tip block has 1 confirmation. Unconfirmed transactions, and those anchored
+/// above the tip, have none.
+pub(crate) fn confirmations(chain_position: &BdkChainPosition<BdkConfirmationBlockTime>, tip_height: u32) -> u32 {
+    match chain_position {
+        BdkChainPosition::Confirmed { anchor, .. } => (tip_height + 1).saturating_sub(anchor.block_id.height),
+        BdkChainPosition::Unconfirmed { .. } => 0,
+    }
+}
+
+/// Direction of a transaction from the point of view of a `Wallet`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TxDirection {
+    /// The wallet only receives funds.
+    Incoming = "incoming",
+    /// The wallet spends funds to other parties.
+    Outgoing = "outgoing",
+    /// The wallet spends funds to itself only, such as a consolidation.
+    SelfTransfer = "self_transfer",
+}
+
+/// A transaction of a `Wallet` with the amounts and addresses involved.
+#[wasm_bindgen]
+pub struct TxDetails {
+    txid: BdkTxid,
+    tx: Arc<BdkTransaction>,
+    sent: BdkAmount,
+    received: BdkAmount,
+    fee: Option<BdkAmount>,
+    fee_rate: Option<BdkFeeRate>,
+    direction: TxDirection,
+    confirmations: u32,
+    chain_position: BdkChainPosition<BdkConfirmationBlockTime>,
+    addresses: Vec<BdkAddressInfo>,
+}
+
+#[wasm_bindgen]
+impl TxDetails {
+    /// Txid of the transaction.
+    #[wasm_bindgen(getter)]
+    pub fn txid(&self) -> Txid {
+        self.txid.into()
+    }
+
+    /// The transaction.
+    #[wasm_bindgen(getter)]
+    pub fn tx(&self) -> Transaction {
+        self.tx.as_ref().clone().into()
+    }
+
+    /// Sum of the wallet's outputs spent by the transaction.
+    #[wasm_bindgen(getter)]
+    pub fn sent(&self) -> Amount {
+        self.sent.into()
+    }
+
+    /// Sum of the transaction outputs sent to the wallet.
+    #[wasm_bindgen(getter)]
+    pub fn received(&self) -> Amount {
+        self.received.into()
+    }
+
+    /// Net effect of the transaction on the balance of the wallet, in satoshis.
+    #[wasm_bindgen(getter)]
+    pub fn net(&self) -> i64 {
+        self.received.to_sat() as i64 - self.sent.to_sat() as i64
+    }
+
+    /// Fee paid by the transaction.
+    ///
+    /// This value will be `None` if the outputs spent by inputs not owned by the wallet are unknown.
+    #[wasm_bindgen(getter)]
+    pub fn fee(&self) -> Option<Amount> {
+        self.fee.map(Into::into)
+    }
+
+    /// Fee rate paid by the transaction.
+    ///
+    /// This value will be `None` if the outputs spent by inputs not owned by the wallet are unknown.
+    #[wasm_bindgen(getter)]
+    pub fn fee_rate(&self) -> Option<FeeRate> {
+        self.fee_rate.map(Into::into)
+    }
+
+    /// Direction of the transaction.
+    #[wasm_bindgen(getter)]
+    pub fn direction(&self) -> TxDirection {
+        self.direction
+    }
+
+    /// Number of confirmations relative to the latest checkpoint of the wallet, 0 if unconfirmed.
+    #[wasm_bindgen(getter)]
+    pub fn confirmations(&self) -> u32 {
+        self.confirmations
+    }
+
+    /// How the transaction is observed in the canonical chain (confirmed or unconfirmed).
+    #[wasm_bindgen(getter)]
+    pub fn chain_position(&self) -> ChainPosition {
+        self.chain_position.into()
+    }
+
+    /// Addresses of the wallet spent from or received to by the transaction.
+    #[wasm_bindgen(getter)]
+    pub fn addresses(&self) -> Vec<AddressInfo> {
+        self.addresses.iter().cloned().map(Into::into).collect()
+    }
+}
+
+impl TxDetails {
+    pub(crate) fn new(wallet: &BdkWallet, wallet_tx: BdkWalletTx) -> Self {
+        let tx = wallet_tx.tx_node.tx;
+        let (sent, received) = wallet.sent_and_received(&tx);
+
+        let direction = if sent == BdkAmount::ZERO {
+            TxDirection::Incoming
+        } else if tx
+            .output
+            .iter()
+            .all(|txout| wallet.is_mine(txout.script_pubkey.clone()))
+        {
+            TxDirection::SelfTransfer
+        } else {
+            TxDirection::Outgoing
+        };
+
+        let confirmations = confirmations(&wallet_tx.chain_position, wallet.latest_checkpoint().height());
+
+        let spent_spks = tx
+            .input
+            .iter()
+            .filter_map(|txin| wallet.tx_graph().get_txout(txin.previous_output))
+            .map(|txout| txout.script_pubkey.clone());
+        let received_spks = tx.output.iter().map(|txout| txout.script_pubkey.clone());
+        let derivations: BTreeSet<(BdkKeychainKind, u32, BdkScriptBuf)> = spent_spks
+            .chain(received_spks)
+            .filter_map(|spk| {
+                let (keychain, index) = *wallet.spk_index().index_of_spk(spk.clone())?;
+                Some((keychain, index, spk))
+            })
+            .collect();
+        let addresses = derivations
+            .into_iter()
+            .filter_map(|(keychain, index, spk)| {
+                let address = BdkAddress::from_script(&spk, wallet.network()).ok()?;
+                Some(BdkAddressInfo {
+                    index,
+                    address,
+                    keychain,
+                })
+            })
+            .collect();
+
+        TxDetails {
+            txid: wallet_tx.tx_node.txid,
+            sent,
+            received,
+            fee: wallet.calculate_fee(&tx).ok(),
+            fee_rate: wallet.calculate_fee_rate(&tx).ok(),
+            direction,
+            confirmations,
+            chain_position: wallet_tx.chain_position,
+            addresses,
+            tx,
+        }
+    }
+}
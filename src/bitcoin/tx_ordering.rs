@@ -0,0 +1,136 @@
+use std::{cmp::Ordering, sync::Arc};
+
+use bdk_wallet::{
+    bitcoin::{hashes::Hash, TxIn as BdkTxIn, TxOut as BdkTxOut},
+    TxOrdering as BdkTxOrdering,
+};
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use web_sys::js_sys::Function;
+
+use crate::{
+    types::{BdkError, BdkErrorCode, TxIn, TxOut},
+    utils::{js_error_message, ErrorStash},
+};
+
+/// Ordering of the transaction's inputs and outputs
+#[derive(Clone, Copy, Default)]
+#[wasm_bindgen]
+pub enum TxOrdering {
+    /// Randomized (default)
+    #[default]
+    Shuffle,
+    /// Unchanged
+    Untouched,
+    /// Lexicographic ordering as per BIP69: inputs by previous txid then output index, outputs by
+    /// amount then script pubkey
+    Bip69,
+}
+
+impl From<TxOrdering> for BdkTxOrdering {
+    fn from(ordering: TxOrdering) -> Self {
+        match ordering {
+            TxOrdering::Shuffle => BdkTxOrdering::Shuffle,
+            TxOrdering::Untouched => BdkTxOrdering::Untouched,
+            TxOrdering::Bip69 => BdkTxOrdering::Custom {
+                input_sort: Arc::new(bip69_input_cmp),
+                output_sort: Arc::new(bip69_output_cmp),
+            },
+        }
+    }
+}
+
+// Txids are compared in their displayed byte order, which is the reverse of their internal one.
+fn bip69_input_cmp(a: &BdkTxIn, b: &BdkTxIn) -> Ordering {
+    let txid = |txin: &BdkTxIn| {
+        let mut bytes = txin.previous_output.txid.to_byte_array();
+        bytes.reverse();
+        bytes
+    };
+    txid(a)
+        .cmp(&txid(b))
+        .then(a.previous_output.vout.cmp(&b.previous_output.vout))
+}
+
+fn bip69_output_cmp(a: &BdkTxOut, b: &BdkTxOut) -> Ordering {
+    a.value
+        .cmp(&b.value)
+        .then_with(|| a.script_pubkey.as_bytes().cmp(b.script_pubkey.as_bytes()))
+}
+
+/// Ordering delegated to JS comparators of inputs and outputs.
+///
+/// Each comparator is called with two `TxIn` or `TxOut` and returns a negative number, zero or a
+/// positive number, like the comparators of `Array.prototype.sort`.
+///
+/// Errors of the comparators are kept in an [`ErrorStash`], retrieved with `take_error`.
+#[derive(Clone)]
+pub(crate) struct JsTxOrdering {
+    input_sort: Function,
+    output_sort: Function,
+    error: ErrorStash,
+}
+
+// SAFETY: Wasm is single-threaded; the comparators are never called concurrently.
+unsafe impl Send for JsTxOrdering {}
+// SAFETY: Wasm is single-threaded; the `ErrorStash` is never accessed from another thread. `Sync` is
+// required by the `Arc<dyn Fn + Send + Sync>` comparators of `TxOrdering::Custom`.
+unsafe impl Sync for JsTxOrdering {}
+
+impl JsTxOrdering {
+    pub(crate) fn new(input_sort: Function, output_sort: Function) -> Self {
+        JsTxOrdering {
+            input_sort,
+            output_sort,
+            error: ErrorStash::default(),
+        }
+    }
+
+    pub(crate) fn take_error(&self) -> Option<BdkError> {
+        self.error.take()
+    }
+
+    // After an error, the remaining comparisons are skipped so that the comparator is not called again.
+    fn compare(&self, compare: &Function, a: JsValue, b: JsValue) -> Ordering {
+        if self.error.is_set() {
+            return Ordering::Equal;
+        }
+
+        let result = compare.call2(&JsValue::NULL, &a, &b).map_err(|e| js_error_message(&e));
+
+        match result.map(|value| value.as_f64()) {
+            Ok(Some(order)) => order.partial_cmp(&0.0).unwrap_or(Ordering::Equal),
+            Ok(None) => {
+                self.error.set(BdkError::new(
+                    BdkErrorCode::OrderingFailed,
+                    "Ordering comparator must return a number",
+                    (),
+                ));
+                Ordering::Equal
+            }
+            Err(message) => {
+                self.error.set(BdkError::new(BdkErrorCode::OrderingFailed, message, ()));
+                Ordering::Equal
+            }
+        }
+    }
+}
+
+impl From<JsTxOrdering> for BdkTxOrdering {
+    fn from(ordering: JsTxOrdering) -> Self {
+        let input_ordering = ordering.clone();
+        let output_ordering = ordering;
+
+        BdkTxOrdering::Custom {
+            input_sort: Arc::new(move |a: &BdkTxIn, b: &BdkTxIn| {
+                input_ordering.compare(&input_ordering.input_sort, TxIn::from(a).into(), TxIn::from(b).into())
+            }),
+            output_sort: Arc::new(move |a: &BdkTxOut, b: &BdkTxOut| {
+                output_ordering.compare(
+                    &output_ordering.output_sort,
+                    TxOut::from(a).into(),
+                    TxOut::from(b).into(),
+                )
+            }),
+        }
+    }
+}
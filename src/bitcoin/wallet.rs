@@ -1,20 +1,31 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashSet},
+    rc::Rc,
+};
 
-use bdk_wallet::{SignOptions as BdkSignOptions, Wallet as BdkWallet};
+use bdk_wallet::{
+    bitcoin::{Amount as BdkAmount, Txid as BdkTxid},
+    SignOptions as BdkSignOptions, Wallet as BdkWallet,
+};
 use wasm_bindgen::{prelude::wasm_bindgen, JsError};
-use web_sys::js_sys::Date;
+use web_sys::js_sys::{Date, Function};
 
 use crate::{
     bitcoin::WalletTx,
     result::JsResult,
     types::{
-        AddressInfo, Amount, Balance, ChangeSet, CheckPoint, FeeRate, FullScanRequest, KeychainKind, LocalOutput,
-        Network, NetworkKind, OutPoint, Psbt, ScriptBuf, SentAndReceived, SpkIndexed, SyncRequest, Transaction, Txid,
-        Update,
+        AddressBalance, AddressInfo, Amount, Balance, BalancePoint, BdkError, BdkErrorCode, ChangeSet, CheckPoint,
+        FeeRate, FullScanRequest, KeychainKind, LocalOutput, Network, NetworkKind, OutPoint, Policy, Psbt, Recipient,
+        ScriptBuf, SentAndReceived, SpkIndexed, SyncRequest, Transaction, Txid, Update,
     },
 };
 
-use super::{TxBuilder, UnconfirmedTx};
+use super::{
+    balance, confirmations, tx_builder::CpfpParent, EvictedTx, FullScanRequestBuilder, NonCanonicalTx,
+    ProgressCallback, QueryOptions, SyncRequestBuilder, TxBuilder, TxDetails, TxOrdering, UnconfirmedTx, WalletEvent,
+    WalletSnapshot,
+};
 
 // We wrap a `BdkWallet` in `Rc<RefCell<...>>` because `wasm_bindgen` do not
 // support Rust's lifetimes. This allows us to forward a reference to the
@@ -58,20 +69,39 @@ impl Wallet {
         Ok(Wallet(Rc::new(RefCell::new(wallet))))
     }
 
-    pub fn start_full_scan(&self) -> FullScanRequest {
-        self.0
-            .borrow()
-            .start_full_scan_at((Date::now() / 1000.0) as u64)
-            .build()
-            .into()
+    /// Create a request to scan every keychain of the wallet until the stop gap is reached.
+    ///
+    /// The `progress` function, if any, is called with a [`ScanProgress`] for every script queued for checking.
+    pub fn start_full_scan(&self, progress: Option<Function>) -> FullScanRequest {
+        let mut builder = self.0.borrow().start_full_scan_at((Date::now() / 1000.0) as u64);
+        if let Some(progress) = progress {
+            builder = builder.inspect(ProgressCallback::new(progress).full_scan());
+        }
+        builder.build().into()
     }
 
-    pub fn start_sync_with_revealed_spks(&self) -> SyncRequest {
-        self.0
+    /// Create a request to sync the revealed scripts of the wallet and its unconfirmed transactions.
+    ///
+    /// The `progress` function, if any, is called with a [`ScanProgress`] for every item queued for checking.
+    pub fn start_sync_with_revealed_spks(&self, progress: Option<Function>) -> SyncRequest {
+        let mut builder = self
+            .0
             .borrow()
-            .start_sync_with_revealed_spks_at((Date::now() / 1000.0) as u64)
-            .build()
-            .into()
+            .start_sync_with_revealed_spks_at((Date::now() / 1000.0) as u64);
+        if let Some(progress) = progress {
+            builder = builder.inspect(ProgressCallback::new(progress).sync());
+        }
+        builder.build().into()
+    }
+
+    /// Create a [`SyncRequestBuilder`] to sync only the chosen scripts, transactions and outputs.
+    pub fn build_sync_request(&self) -> SyncRequestBuilder {
+        SyncRequestBuilder::new(self.0.clone())
+    }
+
+    /// Create a [`FullScanRequestBuilder`] to scan the keychains with their own stop gap.
+    pub fn build_full_scan_request(&self) -> FullScanRequestBuilder {
+        FullScanRequestBuilder::new(self.0.clone())
     }
 
     pub fn apply_update(&self, update: Update) -> JsResult<()> {
@@ -79,6 +109,15 @@ impl Wallet {
         Ok(())
     }
 
+    /// Apply an update like [`apply_update`] and return what happened to the wallet: transactions
+    /// received, sent, confirmed, reorged, replaced or dropped, addresses used and balance changes.
+    pub fn apply_update_events(&self, update: Update) -> JsResult<Vec<WalletEvent>> {
+        let mut wallet = self.0.borrow_mut();
+        let snapshot = WalletSnapshot::new(&wallet);
+        wallet.apply_update(update)?;
+        Ok(snapshot.events(&wallet))
+    }
+
     #[wasm_bindgen(getter)]
     pub fn network(&self) -> Network {
         self.0.borrow().network().into()
@@ -94,6 +133,55 @@ impl Wallet {
         self.0.borrow().balance().into()
     }
 
+    /// Get the balance of the outputs of the given `keychain`.
+    pub fn keychain_balance(&self, keychain: KeychainKind) -> Balance {
+        let wallet = self.0.borrow();
+        let keychain = keychain.into();
+        let outpoints = wallet
+            .spk_index()
+            .keychain_outpoints(keychain)
+            .map(|(index, outpoint)| ((keychain, index), outpoint));
+        balance::balance(&wallet, outpoints).into()
+    }
+
+    /// Get the balance of the address of the given `keychain` at `index`.
+    pub fn address_balance(&self, keychain: KeychainKind, index: u32) -> Balance {
+        let wallet = self.0.borrow();
+        let keychain = keychain.into();
+        let outpoints = wallet
+            .spk_index()
+            .keychain_outpoints(keychain)
+            .filter(|(i, _)| *i == index)
+            .map(|(index, outpoint)| ((keychain, index), outpoint));
+        balance::balance(&wallet, outpoints).into()
+    }
+
+    /// List the balance of every revealed address of the given `keychain`, by derivation index.
+    pub fn address_balances(&self, keychain: KeychainKind) -> Vec<AddressBalance> {
+        balance::address_balances(&self.0.borrow(), keychain.into())
+    }
+
+    /// Get the confirmed balance of the wallet as of the block at `height`.
+    ///
+    /// Only transactions confirmed at or below `height` in the current chain are accounted for.
+    pub fn balance_at_height(&self, height: u32) -> Amount {
+        balance::confirmed_balance_at(&self.0.borrow(), |anchor| anchor.block_id.height <= height).into()
+    }
+
+    /// Get the confirmed balance of the wallet as of the unix timestamp `time`.
+    ///
+    /// Only transactions confirmed in blocks with a confirmation time at or before `time` are
+    /// accounted for.
+    pub fn balance_at_time(&self, time: u64) -> Amount {
+        balance::confirmed_balance_at(&self.0.borrow(), |anchor| anchor.confirmation_time <= time).into()
+    }
+
+    /// List the confirmed balance of the wallet after each block that changed it, from the oldest
+    /// to the most recent.
+    pub fn balance_history(&self) -> Vec<BalancePoint> {
+        balance::balance_history(&self.0.borrow())
+    }
+
     pub fn next_unused_address(&self, keychain: KeychainKind) -> AddressInfo {
         self.0.borrow_mut().next_unused_address(keychain.into()).into()
     }
@@ -130,6 +218,18 @@ impl Wallet {
         self.0.borrow().list_output().map(Into::into).collect()
     }
 
+    /// List the unspent outputs of the wallet matching `options`, sorted and paginated.
+    pub fn query_unspent(&self, options: &QueryOptions) -> Vec<LocalOutput> {
+        let wallet = self.0.borrow();
+        options.query_outputs(&wallet, wallet.list_unspent())
+    }
+
+    /// List the outputs of the wallet, spent or unspent, matching `options`, sorted and paginated.
+    pub fn query_outputs(&self, options: &QueryOptions) -> Vec<LocalOutput> {
+        let wallet = self.0.borrow();
+        options.query_outputs(&wallet, wallet.list_output())
+    }
+
     pub fn get_utxo(&self, op: OutPoint) -> Option<LocalOutput> {
         self.0.borrow().get_utxo(op.into()).map(Into::into)
     }
@@ -142,6 +242,24 @@ impl Wallet {
         self.0.borrow().get_tx(txid.into()).map(Into::into)
     }
 
+    /// List the transactions of the wallet with their amounts, fee, direction and confirmations,
+    /// unconfirmed first and then from the most recent to the oldest.
+    pub fn history(&self) -> Vec<TxDetails> {
+        QueryOptions::new().query_txs(&self.0.borrow())
+    }
+
+    /// List the transactions of the wallet matching `options`, sorted and paginated, with the same
+    /// details as [`history`].
+    pub fn query_transactions(&self, options: &QueryOptions) -> Vec<TxDetails> {
+        options.query_txs(&self.0.borrow())
+    }
+
+    /// Get the details of a transaction of the wallet, as listed by [`history`].
+    pub fn tx_details(&self, txid: Txid) -> Option<TxDetails> {
+        let wallet = self.0.borrow();
+        wallet.get_tx(txid.into()).map(|tx| TxDetails::new(&wallet, tx))
+    }
+
     #[wasm_bindgen(getter)]
     pub fn latest_checkpoint(&self) -> CheckPoint {
         self.0.borrow().latest_checkpoint().into()
@@ -164,10 +282,109 @@ impl Wallet {
         self.0.borrow().derivation_index(keychain.into())
     }
 
+    /// Return the spending policies for the wallet's descriptor.
+    ///
+    /// When the policy [`Policy::requires_path`], a spending branch must be chosen with
+    /// [`TxBuilder::policy_path`] to build a transaction.
+    pub fn policies(&self, keychain: KeychainKind) -> Result<Option<Policy>, BdkError> {
+        let policy = self
+            .0
+            .borrow()
+            .policies(keychain.into())
+            .map_err(|e| BdkError::new(BdkErrorCode::Descriptor, e.to_string(), ()))?;
+        Ok(policy.map(Into::into))
+    }
+
     pub fn build_tx(&self) -> TxBuilder {
         TxBuilder::new(self.0.clone())
     }
 
+    /// Bump the fee of a transaction previously created with this wallet.
+    ///
+    /// Returns an error if the transaction is already confirmed or doesn't explicitly signal
+    /// *replace by fee* (RBF). If the transaction can be fee bumped then it returns a [`TxBuilder`]
+    /// pre-populated with the inputs and outputs of the original transaction. The change output,
+    /// if any, is recomputed so that it absorbs the increased fee.
+    ///
+    /// A new fee rate (or absolute fee) must be set on the returned builder, it must be higher
+    /// than the one of the original transaction.
+    pub fn build_fee_bump(&self, txid: Txid) -> Result<TxBuilder, BdkError> {
+        let txid = txid.into();
+
+        // Fail early if the transaction cannot be replaced, the builder is re-created in `TxBuilder::finish`.
+        self.0.borrow_mut().build_fee_bump(txid)?;
+        Ok(TxBuilder::new_fee_bump(self.0.clone(), txid))
+    }
+
+    /// Accelerate an unconfirmed transaction by spending its outputs with a child transaction (CPFP).
+    ///
+    /// Returns an error if the parent transaction is unknown, already confirmed or has no output
+    /// spendable by this wallet. The returned [`TxBuilder`] spends all the wallet's outputs of the
+    /// parent back to an internal address, unless [`TxBuilder::drain_to`] is used.
+    ///
+    /// The fee rate set on the builder is the target for the parent and child *package*: the
+    /// child pays the fee missing from the parent on top of its own, an absolute fee is ignored.
+    /// This works for received transactions as well, as long as the fee of the parent can be
+    /// computed (its previous outputs are known by the wallet).
+    pub fn build_cpfp(&self, parent_txid: Txid) -> Result<TxBuilder, BdkError> {
+        let parent_txid = parent_txid.into();
+
+        // Fail early if the parent cannot be accelerated, it is inspected again in `TxBuilder::finish`.
+        CpfpParent::new(&self.0.borrow(), parent_txid)?;
+        Ok(TxBuilder::new_cpfp(self.0.clone(), parent_txid))
+    }
+
+    /// Return the maximum amount that can be sent to `destination` at `fee_rate`.
+    ///
+    /// The whole balance allowed by `options` is spent: the fixed recipients of `options` are paid and
+    /// `destination` receives what remains after the fee, which is zero if it is below the dust limit.
+    /// Fails with an `InsufficientFunds` error if the fixed recipients can't be paid. Nothing is changed
+    /// in the wallet.
+    pub fn max_sendable(
+        &self,
+        destination: ScriptBuf,
+        fee_rate: FeeRate,
+        options: SendMaxOptions,
+    ) -> Result<Amount, BdkError> {
+        let mut unspendable = options.unspendable;
+        if options.min_confirmations > 0 {
+            let wallet = self.0.borrow();
+            let tip_height = wallet.latest_checkpoint().height();
+            unspendable.extend(
+                wallet
+                    .list_unspent()
+                    .filter(|utxo| confirmations(&utxo.chain_position, tip_height) < options.min_confirmations)
+                    .map(|utxo| utxo.outpoint.into()),
+            );
+        }
+
+        let recipients = options.recipients.len();
+        // Outputs are left untouched so that the drain output comes after the recipients.
+        let builder = TxBuilder::new(self.0.clone())
+            .set_recipients(options.recipients)
+            .unspendable(unspendable)
+            .fee_rate(fee_rate)
+            .ordering(TxOrdering::Untouched)
+            .drain_wallet()
+            .drain_to(destination);
+
+        let psbt = match builder.create_dry_run(&mut self.0.borrow_mut()) {
+            Ok((psbt, _)) => psbt,
+            // Without recipients to pay, not having enough funds only means that nothing can be sent.
+            Err(e) if recipients == 0 && matches!(e.code(), BdkErrorCode::InsufficientFunds) => {
+                return Ok(BdkAmount::ZERO.into())
+            }
+            Err(e) => return Err(e),
+        };
+        let amount = psbt
+            .unsigned_tx
+            .output
+            .get(recipients)
+            .map(|txout| txout.value)
+            .unwrap_or_default();
+        Ok(amount.into())
+    }
+
     pub fn calculate_fee(&self, tx: Transaction) -> JsResult<Amount> {
         let fee = self.0.borrow().calculate_fee(&tx.into())?;
         Ok(fee.into())
@@ -199,6 +416,104 @@ impl Wallet {
             .borrow_mut()
             .apply_unconfirmed_txs(unconfirmed_txs.into_iter().map(Into::into))
     }
+
+    /// Apply transactions that have been evicted from the mempool, at the given unix timestamps.
+    ///
+    /// An eviction is ignored if it's older than the last time the transaction was seen, or if the
+    /// transaction is confirmed. Evicted transactions, and the ones spending their outputs, are left
+    /// out of the canonical history and the balance until they are seen again.
+    ///
+    /// This stages the changes which need to be persisted.
+    pub fn apply_evicted_txs(&self, evicted_txs: Vec<EvictedTx>) {
+        self.0
+            .borrow_mut()
+            .apply_evicted_txs(evicted_txs.into_iter().map(Into::into))
+    }
+
+    /// List the transactions of the wallet that are not canonical, i.e. evicted from the mempool,
+    /// replaced by a conflicting transaction or spending the outputs of such a transaction.
+    pub fn non_canonical_txs(&self) -> Vec<NonCanonicalTx> {
+        let wallet = self.0.borrow();
+        let canonical_txids: HashSet<BdkTxid> = wallet.transactions().map(|tx| tx.tx_node.txid).collect();
+        let tx_graph = wallet.tx_graph();
+
+        tx_graph
+            .full_txs()
+            .filter(|tx_node| !canonical_txids.contains(&tx_node.txid))
+            .map(|tx_node| {
+                let replaced_by = tx_graph
+                    .direct_conflicts(&tx_node.tx)
+                    .map(|(_, txid)| txid)
+                    .filter(|txid| canonical_txids.contains(txid))
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect();
+                (tx_node, replaced_by).into()
+            })
+            .collect()
+    }
+
+    /// Marks an address used of the given `keychain` at `index`.
+    ///
+    /// Returns whether the given index was present and then removed from the unused set.
+    pub fn mark_used(&self, keychain: KeychainKind, index: u32) -> bool {
+        self.0.borrow_mut().mark_used(keychain.into(), index)
+    }
+
+    /// Undoes the effect of [`mark_used`] and returns whether the `index` was inserted
+    /// back into the unused set.
+    ///
+    /// Since this is only a superficial marker, it will have no effect if the address at the given
+    /// `index` was actually used, i.e. the wallet has previously indexed a tx output for the
+    /// derived spk.
+    pub fn unmark_used(&self, keychain: KeychainKind, index: u32) -> bool {
+        self.0.borrow_mut().unmark_used(keychain.into(), index)
+    }
+
+    /// Informs the wallet that you no longer intend to broadcast a tx that was built from it.
+    ///
+    /// This frees up the change address used when creating the tx for use in future transactions.
+    pub fn cancel_tx(&self, tx: &Transaction) {
+        self.0.borrow_mut().cancel_tx(tx);
+    }
+
+    /// Abandon an unconfirmed transaction of the wallet that will not be broadcast again.
+    ///
+    /// The transaction, and the ones spending its outputs, are evicted from the canonical view of the
+    /// wallet: its inputs become spendable again and it no longer counts in the balance. Its change
+    /// address is freed up like with [`cancel_tx`]. The transaction becomes canonical again if it is
+    /// later seen in the mempool or on-chain.
+    ///
+    /// Returns an error if the transaction is unknown or already confirmed. This stages the changes
+    /// which need to be persisted.
+    pub fn abandon_tx(&self, txid: Txid) -> Result<(), BdkError> {
+        let txid = txid.into();
+        let (tx, last_seen) = {
+            let wallet = self.0.borrow();
+            let wallet_tx = wallet.get_tx(txid).ok_or_else(|| {
+                BdkError::new(
+                    BdkErrorCode::TransactionNotFound,
+                    format!("Transaction not found in the internal database with txid: {txid}"),
+                    txid,
+                )
+            })?;
+            if wallet_tx.chain_position.is_confirmed() {
+                return Err(BdkError::new(
+                    BdkErrorCode::TransactionConfirmed,
+                    format!("Transaction already confirmed with txid: {txid}"),
+                    txid,
+                ));
+            }
+            (wallet_tx.tx_node.tx.clone(), wallet_tx.tx_node.last_seen)
+        };
+
+        // The eviction only applies if it's not older than the last time the transaction was seen.
+        let evicted_at = ((Date::now() / 1000.0) as u64).max(last_seen.unwrap_or_default());
+        let mut wallet = self.0.borrow_mut();
+        wallet.apply_evicted_txs([(txid, evicted_at)]);
+        wallet.cancel_tx(&tx);
+        Ok(())
+    }
 }
 
 #[wasm_bindgen]
@@ -283,3 +598,53 @@ impl Default for SignOptions {
         Self::new()
     }
 }
+
+/// Options of [`Wallet::max_sendable`].
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct SendMaxOptions {
+    recipients: Vec<Recipient>,
+    unspendable: Vec<OutPoint>,
+    min_confirmations: u32,
+}
+
+#[wasm_bindgen]
+impl SendMaxOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        SendMaxOptions::default()
+    }
+
+    /// Recipients paid alongside the destination, with fixed amounts
+    #[wasm_bindgen(getter)]
+    pub fn recipients(&self) -> Vec<Recipient> {
+        self.recipients.clone()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_recipients(&mut self, recipients: Vec<Recipient>) {
+        self.recipients = recipients;
+    }
+
+    /// UTXOs that must not be spent
+    #[wasm_bindgen(getter)]
+    pub fn unspendable(&self) -> Vec<OutPoint> {
+        self.unspendable.clone()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_unspendable(&mut self, unspendable: Vec<OutPoint>) {
+        self.unspendable = unspendable;
+    }
+
+    /// Minimum number of confirmations of the UTXOs to spend, `0` to spend unconfirmed UTXOs (default)
+    #[wasm_bindgen(getter)]
+    pub fn min_confirmations(&self) -> u32 {
+        self.min_confirmations
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_min_confirmations(&mut self, value: u32) {
+        self.min_confirmations = value;
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use bdk_wallet::{
+    bitcoin::{Amount as BdkAmount, Txid as BdkTxid},
+    chain::{ChainPosition as BdkChainPosition, ConfirmationBlockTime as BdkConfirmationBlockTime},
+    AddressInfo as BdkAddressInfo, Balance as BdkBalance, KeychainKind as BdkKeychainKind, Wallet as BdkWallet,
+};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::types::{AddressInfo, Balance, ChainPosition, Txid};
+
+/// Kind of a [`WalletEvent`].
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum WalletEventKind {
+    /// A new transaction receives funds to the wallet without spending from it.
+    TxReceived = "tx_received",
+    /// A new transaction spends funds of the wallet.
+    TxSent = "tx_sent",
+    /// A transaction is confirmed, or confirmed in another block after a reorg.
+    TxConfirmed = "tx_confirmed",
+    /// A confirmed transaction is back to unconfirmed after a reorg.
+    TxReorged = "tx_reorged",
+    /// A transaction is replaced by a conflicting transaction.
+    TxReplaced = "tx_replaced",
+    /// A transaction is evicted from the mempool without being replaced.
+    TxDropped = "tx_dropped",
+    /// The balance of the wallet changed.
+    BalanceChanged = "balance_changed",
+    /// An address of the wallet received its first output.
+    AddressUsed = "address_used",
+}
+
+/// Something that happened to a `Wallet` when applying an update.
+///
+/// Transaction events hold the `txid` and new `chain_position` of the transaction, a `tx_replaced`
+/// event also holds the transactions replacing it in `conflicts`. A `balance_changed` event holds
+/// the new `balance` and an `address_used` event holds the `address`.
+#[wasm_bindgen]
+pub struct WalletEvent {
+    kind: WalletEventKind,
+    txid: Option<BdkTxid>,
+    chain_position: Option<BdkChainPosition<BdkConfirmationBlockTime>>,
+    conflicts: Vec<BdkTxid>,
+    balance: Option<BdkBalance>,
+    address: Option<BdkAddressInfo>,
+}
+
+#[wasm_bindgen]
+impl WalletEvent {
+    /// Kind of the event
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> WalletEventKind {
+        self.kind
+    }
+
+    /// Txid of the transaction of a transaction event
+    #[wasm_bindgen(getter)]
+    pub fn txid(&self) -> Option<Txid> {
+        self.txid.map(Into::into)
+    }
+
+    /// Position of the transaction in the canonical chain after the update, `undefined` if it's no
+    /// longer canonical
+    #[wasm_bindgen(getter)]
+    pub fn chain_position(&self) -> Option<ChainPosition> {
+        self.chain_position.map(Into::into)
+    }
+
+    /// Txids of the canonical transactions replacing the transaction of a `tx_replaced` event
+    #[wasm_bindgen(getter)]
+    pub fn conflicts(&self) -> Vec<Txid> {
+        self.conflicts.iter().copied().map(Into::into).collect()
+    }
+
+    /// Balance of the wallet after the update of a `balance_changed` event
+    #[wasm_bindgen(getter)]
+    pub fn balance(&self) -> Option<Balance> {
+        self.balance.clone().map(Into::into)
+    }
+
+    /// Address of an `address_used` event
+    #[wasm_bindgen(getter)]
+    pub fn address(&self) -> Option<AddressInfo> {
+        self.address.clone().map(Into::into)
+    }
+}
+
+impl WalletEvent {
+    fn new(kind: WalletEventKind) -> Self {
+        WalletEvent {
+            kind,
+            txid: None,
+            chain_position: None,
+            conflicts: vec![],
+            balance: None,
+            address: None,
+        }
+    }
+
+    fn tx(
+        kind: WalletEventKind,
+        txid: BdkTxid,
+        chain_position: Option<BdkChainPosition<BdkConfirmationBlockTime>>,
+    ) -> Self {
+        WalletEvent {
+            txid: Some(txid),
+            chain_position,
+            ..WalletEvent::new(kind)
+        }
+    }
+}
+
+/// State of a wallet compared before and after an update to produce [`WalletEvent`]s.
+pub(crate) struct WalletSnapshot {
+    txs: BTreeMap<BdkTxid, BdkChainPosition<BdkConfirmationBlockTime>>,
+    balance: BdkBalance,
+    used: BTreeSet<(BdkKeychainKind, u32)>,
+}
+
+impl WalletSnapshot {
+    pub(crate) fn new(wallet: &BdkWallet) -> Self {
+        WalletSnapshot {
+            txs: wallet
+                .transactions()
+                .map(|tx| (tx.tx_node.txid, tx.chain_position))
+                .collect(),
+            balance: wallet.balance(),
+            used: wallet
+                .spk_index()
+                .outpoints()
+                .iter()
+                .map(|(derivation, _)| *derivation)
+                .collect(),
+        }
+    }
+
+    /// Events that happened since this snapshot was taken, in the order: addresses used,
+    /// transactions and balance.
+    pub(crate) fn events(self, wallet: &BdkWallet) -> Vec<WalletEvent> {
+        let after = WalletSnapshot::new(wallet);
+        let mut events = vec![];
+
+        for &(keychain, index) in after.used.difference(&self.used) {
+            events.push(WalletEvent {
+                address: Some(wallet.peek_address(keychain, index)),
+                ..WalletEvent::new(WalletEventKind::AddressUsed)
+            });
+        }
+
+        for (&txid, &chain_position) in &after.txs {
+            let previous = self.txs.get(&txid);
+            if previous.is_none() {
+                let tx = wallet.get_tx(txid).expect("canonical transaction must exist");
+                let (sent, _) = wallet.sent_and_received(&tx.tx_node.tx);
+                let kind = if sent == BdkAmount::ZERO {
+                    WalletEventKind::TxReceived
+                } else {
+                    WalletEventKind::TxSent
+                };
+                events.push(WalletEvent::tx(kind, txid, Some(chain_position)));
+            }
+
+            match (previous, &chain_position) {
+                (
+                    Some(BdkChainPosition::Confirmed { anchor: previous, .. }),
+                    BdkChainPosition::Confirmed { anchor, .. },
+                ) if previous.block_id == anchor.block_id => {}
+                (_, BdkChainPosition::Confirmed { .. }) => {
+                    events.push(WalletEvent::tx(
+                        WalletEventKind::TxConfirmed,
+                        txid,
+                        Some(chain_position),
+                    ));
+                }
+                (Some(BdkChainPosition::Confirmed { .. }), BdkChainPosition::Unconfirmed { .. }) => {
+                    events.push(WalletEvent::tx(WalletEventKind::TxReorged, txid, Some(chain_position)));
+                }
+                _ => {}
+            }
+        }
+
+        for &txid in self.txs.keys().filter(|&txid| !after.txs.contains_key(txid)) {
+            let conflicts: Vec<BdkTxid> = wallet
+                .tx_graph()
+                .get_tx(txid)
+                .map(|tx| {
+                    wallet
+                        .tx_graph()
+                        .direct_conflicts(&tx)
+                        .map(|(_, txid)| txid)
+                        .filter(|txid| after.txs.contains_key(txid))
+                        .collect::<BTreeSet<_>>()
+                        .into_iter()
+                        .collect()
+                })
+                .unwrap_or_default();
+            let kind = if conflicts.is_empty() {
+                WalletEventKind::TxDropped
+            } else {
+                WalletEventKind::TxReplaced
+            };
+            events.push(WalletEvent {
+                conflicts,
+                ..WalletEvent::tx(kind, txid, None)
+            });
+        }
+
+        if after.balance != self.balance {
+            events.push(WalletEvent {
+                balance: Some(after.balance),
+                ..WalletEvent::new(WalletEventKind::BalanceChanged)
+            });
+        }
+
+        events
+    }
+}
@@ -2,7 +2,7 @@ use std::{collections::BTreeSet, sync::Arc};
 
 use bdk_wallet::{
     bitcoin::{Transaction as BdkTransaction, Txid as BdkTxid},
-    chain::{ChainPosition as BdkChainPosition, ConfirmationBlockTime as BdkConfirmationBlockTime},
+    chain::{tx_graph::TxNode, ChainPosition as BdkChainPosition, ConfirmationBlockTime as BdkConfirmationBlockTime},
     WalletTx as BdkWalletTx,
 };
 use wasm_bindgen::prelude::wasm_bindgen;
@@ -100,3 +100,97 @@ impl From<UnconfirmedTx> for (Arc<BdkTransaction>, u64) {
         (Arc::new(uctx.0.into()), uctx.1)
     }
 }
+
+/// A transaction that dropped out of the mempool, identified by its txid.
+#[wasm_bindgen]
+pub struct EvictedTx(Txid, pub u64);
+
+#[wasm_bindgen]
+impl EvictedTx {
+    /// `txid` – the `Txid` of the evicted transaction
+    /// `evicted_at` – unix epoch seconds (same convention as the rest of the API)
+    #[wasm_bindgen(constructor)]
+    pub fn new(txid: Txid, evicted_at: u64) -> EvictedTx {
+        EvictedTx(txid, evicted_at)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn txid(&self) -> Txid {
+        (*self.0).into()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn evicted_at(&self) -> u64 {
+        self.1
+    }
+}
+
+impl From<EvictedTx> for (BdkTxid, u64) {
+    fn from(evicted_tx: EvictedTx) -> Self {
+        (evicted_tx.0.into(), evicted_tx.1)
+    }
+}
+
+/// A transaction known by a `Wallet` but left out of its canonical history, because it was evicted
+/// from the mempool, replaced by a conflicting transaction or spends such a transaction.
+#[wasm_bindgen]
+pub struct NonCanonicalTx {
+    txid: BdkTxid,
+    tx: BdkTransaction,
+    last_seen: Option<u64>,
+    first_seen: Option<u64>,
+    replaced_by: Vec<BdkTxid>,
+}
+
+#[wasm_bindgen]
+impl NonCanonicalTx {
+    /// Txid of the transaction.
+    #[wasm_bindgen(getter)]
+    pub fn txid(&self) -> Txid {
+        self.txid.into()
+    }
+
+    /// The transaction.
+    #[wasm_bindgen(getter)]
+    pub fn tx(&self) -> Transaction {
+        self.tx.clone().into()
+    }
+
+    /// The last-seen unix timestamp of the transaction as unconfirmed.
+    #[wasm_bindgen(getter)]
+    pub fn last_seen(&self) -> Option<u64> {
+        self.last_seen
+    }
+
+    /// The first-seen unix timestamp of the transaction as unconfirmed.
+    #[wasm_bindgen(getter)]
+    pub fn first_seen(&self) -> Option<u64> {
+        self.first_seen
+    }
+
+    /// Canonical transactions spending the same outputs as this transaction.
+    ///
+    /// Empty if the transaction was evicted without being replaced.
+    #[wasm_bindgen(getter)]
+    pub fn replaced_by(&self) -> Vec<Txid> {
+        self.replaced_by.iter().copied().map(Into::into).collect()
+    }
+
+    /// Whether the transaction is conflicting with a canonical transaction.
+    #[wasm_bindgen(getter)]
+    pub fn is_replaced(&self) -> bool {
+        !self.replaced_by.is_empty()
+    }
+}
+
+impl<A> From<(TxNode<'_, Arc<BdkTransaction>, A>, Vec<BdkTxid>)> for NonCanonicalTx {
+    fn from((tx_node, replaced_by): (TxNode<'_, Arc<BdkTransaction>, A>, Vec<BdkTxid>)) -> Self {
+        NonCanonicalTx {
+            txid: tx_node.txid,
+            tx: tx_node.tx.as_ref().clone(),
+            last_seen: tx_node.last_seen,
+            first_seen: tx_node.first_seen,
+            replaced_by,
+        }
+    }
+}
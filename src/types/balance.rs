@@ -3,10 +3,11 @@ use std::ops::Deref;
 use bdk_wallet::Balance as BdkBalance;
 use wasm_bindgen::prelude::wasm_bindgen;
 
-use super::Amount;
+use super::{AddressInfo, Amount};
 
 /// Balance, differentiated into various categories.
 #[wasm_bindgen]
+#[derive(Clone)]
 pub struct Balance(BdkBalance);
 
 #[wasm_bindgen]
@@ -64,3 +65,57 @@ impl From<BdkBalance> for Balance {
         Balance(inner)
     }
 }
+
+/// Balance of an address of a wallet.
+#[wasm_bindgen]
+pub struct AddressBalance(pub(crate) AddressInfo, pub(crate) Balance);
+
+#[wasm_bindgen]
+impl AddressBalance {
+    /// Address and the index it was derived at
+    #[wasm_bindgen(getter)]
+    pub fn address_info(&self) -> AddressInfo {
+        self.0.clone()
+    }
+
+    /// Balance of the address
+    #[wasm_bindgen(getter)]
+    pub fn balance(&self) -> Balance {
+        self.1.clone()
+    }
+}
+
+/// Confirmed balance of a wallet once a block is connected.
+#[wasm_bindgen]
+pub struct BalancePoint {
+    height: u32,
+    time: u64,
+    balance: Amount,
+}
+
+#[wasm_bindgen]
+impl BalancePoint {
+    /// Height of the block
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Confirmation time of the block, as a unix timestamp
+    #[wasm_bindgen(getter)]
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// Confirmed balance at this block
+    #[wasm_bindgen(getter)]
+    pub fn balance(&self) -> Amount {
+        self.balance
+    }
+}
+
+impl BalancePoint {
+    pub(crate) fn new(height: u32, time: u64, balance: Amount) -> Self {
+        BalancePoint { height, time, balance }
+    }
+}
@@ -1,14 +1,25 @@
-use std::ops::Deref;
+use std::{ops::Deref, str::FromStr};
 
-use bdk_wallet::chain::{BlockId as BdkBlockId, ConfirmationBlockTime as BdkConfirmationBlockTime};
+use bdk_wallet::{
+    bitcoin::BlockHash,
+    chain::{BlockId as BdkBlockId, ConfirmationBlockTime as BdkConfirmationBlockTime},
+};
 use wasm_bindgen::prelude::wasm_bindgen;
 
+use crate::result::JsResult;
+
 /// A reference to a block in the canonical chain.
 #[wasm_bindgen]
 pub struct BlockId(BdkBlockId);
 
 #[wasm_bindgen]
 impl BlockId {
+    #[wasm_bindgen(constructor)]
+    pub fn new(height: u32, hash: &str) -> JsResult<BlockId> {
+        let hash = BlockHash::from_str(hash)?;
+        Ok(BlockId(BdkBlockId { height, hash }))
+    }
+
     /// The height of the block.
     #[wasm_bindgen(getter)]
     pub fn height(&self) -> u32 {
@@ -28,6 +39,12 @@ impl From<BdkBlockId> for BlockId {
     }
 }
 
+impl From<BlockId> for BdkBlockId {
+    fn from(block_id: BlockId) -> Self {
+        block_id.0
+    }
+}
+
 /// Represents the observed position of some chain data.
 #[wasm_bindgen]
 pub struct ConfirmationBlockTime(BdkConfirmationBlockTime);
@@ -42,6 +59,14 @@ impl Deref for ConfirmationBlockTime {
 
 #[wasm_bindgen]
 impl ConfirmationBlockTime {
+    #[wasm_bindgen(constructor)]
+    pub fn new(block_id: BlockId, confirmation_time: u64) -> Self {
+        ConfirmationBlockTime(BdkConfirmationBlockTime {
+            block_id: block_id.into(),
+            confirmation_time,
+        })
+    }
+
     /// The anchor block.
     #[wasm_bindgen(getter)]
     pub fn block_id(&self) -> BlockId {
@@ -1,18 +1,23 @@
-use std::ops::Deref;
+use std::{collections::BTreeMap, ops::Deref, sync::Arc};
 
 use bdk_wallet::{
+    bitcoin::{BlockHash, Txid as BdkTxid},
     chain::{
         spk_client::{
             FullScanRequest as BdkFullScanRequest, FullScanResponse as BdkFullScanResponse,
             SyncRequest as BdkSyncRequest, SyncResponse as BdkSyncResponse,
         },
-        ChainPosition as BdkChainPosition, ConfirmationBlockTime as BdkConfirmationBlockTime,
+        BlockId as BdkBlockId, ChainPosition as BdkChainPosition, CheckPoint as BdkCheckPoint,
+        ConfirmationBlockTime as BdkConfirmationBlockTime, TxUpdate,
     },
     KeychainKind, Update as BdkUpdate,
 };
-use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
 
-use super::{ConfirmationBlockTime, Txid};
+use super::{
+    BlockId, CheckPoint, ConfirmationBlockTime, KeychainKind as JsKeychainKind, OutPoint, ScriptBuf, Transaction,
+    TxOut, Txid,
+};
 
 /// Data required to perform a spk-based blockchain client sync.
 ///
@@ -29,6 +34,44 @@ impl Deref for SyncRequest {
     }
 }
 
+#[wasm_bindgen]
+impl SyncRequest {
+    /// When the request was created, used as the time unconfirmed transactions are seen.
+    #[wasm_bindgen(getter)]
+    pub fn start_time(&self) -> u64 {
+        self.0.start_time()
+    }
+
+    /// Latest checkpoint of the wallet, that the chain of the update must connect to.
+    #[wasm_bindgen(getter)]
+    pub fn chain_tip(&self) -> Option<CheckPoint> {
+        self.0.chain_tip().map(Into::into)
+    }
+
+    /// Take the scripts to check out of the request, with the transactions expected for each.
+    ///
+    /// An expected transaction no longer found for its script has been evicted from the mempool.
+    pub fn take_spks(&mut self) -> Vec<SpkWithExpectedTxids> {
+        self.0
+            .iter_spks_with_expected_txids()
+            .map(|spk| SpkWithExpectedTxids {
+                spk: spk.spk.into(),
+                expected_txids: spk.expected_txids.into_iter().collect(),
+            })
+            .collect()
+    }
+
+    /// Take the txids to check out of the request.
+    pub fn take_txids(&mut self) -> Vec<Txid> {
+        self.0.iter_txids().map(Into::into).collect()
+    }
+
+    /// Take the outpoints whose spending transaction to check out of the request.
+    pub fn take_outpoints(&mut self) -> Vec<OutPoint> {
+        self.0.iter_outpoints().map(Into::into).collect()
+    }
+}
+
 impl From<BdkSyncRequest<(KeychainKind, u32)>> for SyncRequest {
     fn from(inner: BdkSyncRequest<(KeychainKind, u32)>) -> Self {
         SyncRequest(inner)
@@ -48,25 +91,120 @@ impl From<SyncRequest> for BdkSyncRequest<(KeychainKind, u32)> {
 /// generally only used when importing or restoring previously used keychains in which the list of
 /// used scripts is not known.
 #[wasm_bindgen]
-pub struct FullScanRequest(BdkFullScanRequest<KeychainKind>);
+pub struct FullScanRequest {
+    inner: BdkFullScanRequest<KeychainKind>,
+    stop_gaps: BTreeMap<KeychainKind, usize>,
+}
+
+impl FullScanRequest {
+    pub(crate) fn with_stop_gaps(
+        inner: BdkFullScanRequest<KeychainKind>,
+        stop_gaps: BTreeMap<KeychainKind, usize>,
+    ) -> Self {
+        FullScanRequest { inner, stop_gaps }
+    }
+}
+
+#[wasm_bindgen]
+impl FullScanRequest {
+    /// Stop gap of `keychain` if it has its own, instead of the one given to the client.
+    pub fn stop_gap(&self, keychain: JsKeychainKind) -> Option<usize> {
+        self.stop_gaps.get(&keychain.into()).copied()
+    }
+
+    /// When the request was created, used as the time unconfirmed transactions are seen.
+    #[wasm_bindgen(getter)]
+    pub fn start_time(&self) -> u64 {
+        self.inner.start_time()
+    }
+
+    /// Latest checkpoint of the wallet, that the chain of the update must connect to.
+    #[wasm_bindgen(getter)]
+    pub fn chain_tip(&self) -> Option<CheckPoint> {
+        self.inner.chain_tip().map(Into::into)
+    }
+
+    /// Keychains to scan.
+    #[wasm_bindgen(getter, unchecked_return_type = "KeychainKind[]")]
+    pub fn keychains(&self) -> Vec<JsValue> {
+        self.inner
+            .keychains()
+            .into_iter()
+            .map(|keychain| JsKeychainKind::from(keychain).into())
+            .collect()
+    }
+
+    /// Take the next script of `keychain` to check out of the request, by increasing derivation index.
+    ///
+    /// Scripts are derived without bounds, the caller stops once the stop gap is reached.
+    pub fn next_spk(&mut self, keychain: JsKeychainKind) -> Option<IndexedSpk> {
+        self.inner
+            .next_spk(keychain.into())
+            .map(|(index, spk)| IndexedSpk { index, spk: spk.into() })
+    }
+}
+
+/// A script to check for a [`SyncRequest`], with the transactions expected for it.
+#[wasm_bindgen]
+pub struct SpkWithExpectedTxids {
+    spk: ScriptBuf,
+    expected_txids: Vec<BdkTxid>,
+}
+
+#[wasm_bindgen]
+impl SpkWithExpectedTxids {
+    /// The script pubkey.
+    #[wasm_bindgen(getter)]
+    pub fn spk(&self) -> ScriptBuf {
+        self.spk.clone()
+    }
+
+    /// Txids of the canonical transactions of the wallet involving the script.
+    #[wasm_bindgen(getter)]
+    pub fn expected_txids(&self) -> Vec<Txid> {
+        self.expected_txids.iter().copied().map(Into::into).collect()
+    }
+}
+
+/// A script of a keychain to check for a [`FullScanRequest`], with its derivation index.
+#[wasm_bindgen]
+pub struct IndexedSpk {
+    index: u32,
+    spk: ScriptBuf,
+}
+
+#[wasm_bindgen]
+impl IndexedSpk {
+    /// The derivation index of the script.
+    #[wasm_bindgen(getter)]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// The script pubkey.
+    #[wasm_bindgen(getter)]
+    pub fn spk(&self) -> ScriptBuf {
+        self.spk.clone()
+    }
+}
 
 impl Deref for FullScanRequest {
     type Target = BdkFullScanRequest<KeychainKind>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.inner
     }
 }
 
 impl From<BdkFullScanRequest<KeychainKind>> for FullScanRequest {
     fn from(inner: BdkFullScanRequest<KeychainKind>) -> Self {
-        FullScanRequest(inner)
+        FullScanRequest::with_stop_gaps(inner, BTreeMap::new())
     }
 }
 
 impl From<FullScanRequest> for BdkFullScanRequest<KeychainKind> {
     fn from(request: FullScanRequest) -> Self {
-        request.0
+        request.inner
     }
 }
 
@@ -106,6 +244,94 @@ impl From<BdkSyncResponse> for Update {
     }
 }
 
+/// A builder of [`Update`] from the data fetched by a custom chain source.
+///
+/// The blocks added and the blocks of the anchors form the chain of the update, which must connect
+/// to the chain of the wallet by including one of its blocks, such as the `chain_tip` of the
+/// request. Without blocks nor anchors, the chain of the wallet is left as is.
+///
+/// Each option setting method on `UpdateBuilder` takes and returns a new builder so you can chain calls
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct UpdateBuilder {
+    tx_update: TxUpdate<BdkConfirmationBlockTime>,
+    blocks: BTreeMap<u32, BlockHash>,
+    last_active_indices: BTreeMap<KeychainKind, u32>,
+}
+
+#[wasm_bindgen]
+impl UpdateBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        UpdateBuilder::default()
+    }
+
+    /// Add a transaction relevant to the wallet.
+    pub fn add_tx(mut self, tx: Transaction) -> Self {
+        self.tx_update.txs.push(Arc::new(tx.into()));
+        self
+    }
+
+    /// Add an output without its transaction, such as one spent by a transaction of the wallet to
+    /// compute its fee.
+    pub fn add_txout(mut self, outpoint: OutPoint, txout: TxOut) -> Self {
+        self.tx_update.txouts.insert(outpoint.into(), txout.into());
+        self
+    }
+
+    /// Add the block confirming a transaction.
+    ///
+    /// The block is added to the chain of the update, since a transaction is only confirmed in the
+    /// wallet if its anchor block is part of the chain. A block added with [`add_block`] at the same
+    /// height takes precedence.
+    pub fn add_anchor(mut self, txid: Txid, anchor: ConfirmationBlockTime) -> Self {
+        self.tx_update.anchors.insert((anchor.into(), txid.into()));
+        self
+    }
+
+    /// Add the time an unconfirmed transaction was seen in the mempool.
+    pub fn add_seen_at(mut self, txid: Txid, seen_at: u64) -> Self {
+        self.tx_update.seen_ats.insert((txid.into(), seen_at));
+        self
+    }
+
+    /// Add the time a transaction was found missing from the mempool.
+    pub fn add_evicted_at(mut self, txid: Txid, evicted_at: u64) -> Self {
+        self.tx_update.evicted_ats.insert((txid.into(), evicted_at));
+        self
+    }
+
+    /// Add a block to the chain of the update, replacing any block added at the same height.
+    pub fn add_block(mut self, block_id: BlockId) -> Self {
+        let block_id: BdkBlockId = block_id.into();
+        self.blocks.insert(block_id.height, block_id.hash);
+        self
+    }
+
+    /// Set the last derivation index of `keychain` having transactions, revealing the addresses up to it.
+    pub fn last_active_index(mut self, keychain: JsKeychainKind, index: u32) -> Self {
+        self.last_active_indices.insert(keychain.into(), index);
+        self
+    }
+
+    /// Create the [`Update`].
+    pub fn build(mut self) -> Update {
+        for (anchor, _) in &self.tx_update.anchors {
+            let block_id = anchor.block_id;
+            self.blocks.entry(block_id.height).or_insert(block_id.hash);
+        }
+        let blocks = self
+            .blocks
+            .into_iter()
+            .map(|(height, hash)| BdkBlockId { height, hash });
+        Update(BdkUpdate {
+            last_active_indices: self.last_active_indices,
+            tx_update: self.tx_update,
+            chain: BdkCheckPoint::from_block_ids(blocks).ok(),
+        })
+    }
+}
+
 /// Represents the observed position of some chain data.
 #[wasm_bindgen]
 pub struct ChainPosition(BdkChainPosition<BdkConfirmationBlockTime>);
@@ -3,6 +3,7 @@ use serde_wasm_bindgen::to_value;
 use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
 
 #[wasm_bindgen]
+#[derive(Clone)]
 pub struct BdkError {
     code: BdkErrorCode,
     message: String,
@@ -120,6 +121,64 @@ pub enum BdkErrorCode {
     /// Invalid character in input.
     InvalidCharacter,
 
+    /// ------- Coin selection errors -------
+
+    /// The custom coin selection function threw or returned something else than outpoints
+    CoinSelectionFailed,
+
+    /// ------- Ordering errors -------
+
+    /// A custom ordering comparator threw or returned something else than a number
+    OrderingFailed,
+
+    /// ------- Randomness errors -------
+
+    /// The random bytes function threw or returned something else than the requested bytes
+    RandomnessFailed,
+
+    /// ------- Foreign UTXO errors -------
+
+    /// Foreign utxo outpoint txid does not match PSBT input txid
+    InvalidTxid,
+    /// Requested outpoint doesn't exist in the tx (vout greater than available outputs)
+    InvalidOutpoint,
+    /// Foreign utxo missing witness_utxo or non_witness_utxo
+    MissingUtxo,
+
+    /// ------- Fee bump and CPFP errors -------
+
+    /// Transaction to bump or accelerate is not found in the wallet
+    TransactionNotFound,
+    /// Transaction to bump or accelerate is already confirmed
+    TransactionConfirmed,
+    /// Transaction to bump does not signal replaceability (sequence >= `0xFFFFFFFE`)
+    IrreplaceableTransaction,
+    /// Fee of the transaction to bump or accelerate cannot be computed
+    FeeRateUnavailable,
+    /// An input of the transaction to bump references an invalid output index
+    InvalidOutputIndex,
+    /// Transaction to accelerate with CPFP has no output spendable by the wallet
+    NoSpendableOutputs,
+
+    /// ------- OP_RETURN errors -------
+
+    /// OP_RETURN data exceeds the standard size limit
+    DataTooLarge,
+    /// Data was added several times, but a transaction with more than one OP_RETURN output is non-standard
+    MultipleDataOutputs,
+
+    /// ------- Sequence and locktime errors -------
+
+    /// Cannot enable RBF with a `Sequence` >= 0xFFFFFFFE
+    RbfSequence,
+    /// Requested current height is not a valid block height
+    InvalidHeight,
+
+    /// ------- Subtract fee errors -------
+
+    /// The fee cannot be subtracted from a recipient when bumping the fee of a transaction or with CPFP
+    SubtractFeeUnsupported,
+
     /// ------- Other errors -------
     /// Unexpected error, should never happen
     Unexpected,
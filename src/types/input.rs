@@ -36,6 +36,15 @@ impl TxIn {
         self.0.script_sig.clone().into()
     }
 
+    /// The sequence number, which suggests to miners which of two
+    /// conflicting transactions should be preferred, or 0xFFFFFFFF
+    /// to ignore this feature. This is generally never used since
+    /// the miner behavior cannot be enforced.
+    #[wasm_bindgen(getter)]
+    pub fn sequence(&self) -> u32 {
+        self.0.sequence.to_consensus_u32()
+    }
+
     /// Returns the base size of this input.
     ///
     /// Base size excludes the witness data.
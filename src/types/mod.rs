@@ -11,6 +11,7 @@ mod input;
 mod keychain;
 mod network;
 mod output;
+mod policy;
 mod psbt;
 mod slip10;
 mod transaction;
@@ -28,6 +29,7 @@ pub use input::*;
 pub use keychain::*;
 pub use network::*;
 pub use output::*;
+pub use policy::*;
 pub use psbt::*;
 pub use slip10::*;
 pub use transaction::*;
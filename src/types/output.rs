@@ -76,6 +76,7 @@ impl From<OutPoint> for BdkOutPoint {
 ///
 /// An output that is not yet spent by an input is called Unspent Transaction Output ("UTXO").
 #[wasm_bindgen]
+#[derive(Clone)]
 pub struct TxOut(BdkTxOut);
 
 impl Deref for TxOut {
@@ -88,6 +89,14 @@ impl Deref for TxOut {
 
 #[wasm_bindgen]
 impl TxOut {
+    #[wasm_bindgen(constructor)]
+    pub fn new(value: Amount, script_pubkey: ScriptBuf) -> Self {
+        TxOut(BdkTxOut {
+            value: value.into(),
+            script_pubkey: script_pubkey.into(),
+        })
+    }
+
     /// The value of the output, in satoshis.
     #[wasm_bindgen(getter)]
     pub fn value(&self) -> Amount {
@@ -0,0 +1,123 @@
+use std::ops::Deref;
+
+use bdk_wallet::{
+    descriptor::{policy::SatisfiableItem, Policy as BdkPolicy},
+    serde_json::to_string,
+};
+use serde::Serialize;
+use serde_wasm_bindgen::Serializer;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+
+/// Descriptor spending policy, as a tree of conditions to satisfy.
+///
+/// Each node has an `id` used to choose a spending branch with [`TxBuilder::policy_path`]: for a node
+/// with a threshold lower than the number of its children, the path lists the indexes of the children
+/// to satisfy.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct Policy(BdkPolicy);
+
+#[wasm_bindgen]
+impl Policy {
+    /// Identifier for this policy node
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> String {
+        self.0.id.clone()
+    }
+
+    /// Type of the condition of this node, such as `THRESH`, `MULTISIG`, `ECDSASIGNATURE`,
+    /// `SCHNORRSIGNATURE`, `ABSOLUTETIMELOCK` or `RELATIVETIMELOCK`
+    #[wasm_bindgen(getter)]
+    pub fn item_type(&self) -> String {
+        let item_type = match &self.0.item {
+            SatisfiableItem::EcdsaSignature(_) => "ECDSASIGNATURE",
+            SatisfiableItem::SchnorrSignature(_) => "SCHNORRSIGNATURE",
+            SatisfiableItem::Sha256Preimage { .. } => "SHA256PREIMAGE",
+            SatisfiableItem::Hash256Preimage { .. } => "HASH256PREIMAGE",
+            SatisfiableItem::Ripemd160Preimage { .. } => "RIPEMD160PREIMAGE",
+            SatisfiableItem::Hash160Preimage { .. } => "HASH160PREIMAGE",
+            SatisfiableItem::AbsoluteTimelock { .. } => "ABSOLUTETIMELOCK",
+            SatisfiableItem::RelativeTimelock { .. } => "RELATIVETIMELOCK",
+            SatisfiableItem::Multisig { .. } => "MULTISIG",
+            SatisfiableItem::Thresh { .. } => "THRESH",
+        };
+        item_type.into()
+    }
+
+    /// Condition of this node as an object tagged by its `type`, holding the keys (public keys or
+    /// fingerprints), hashes, timelock `value` or `threshold` of the condition
+    #[wasm_bindgen(getter)]
+    pub fn item(&self) -> JsValue {
+        to_js_value(&self.0.item)
+    }
+
+    /// Number of children, or keys for a `MULTISIG` node, that must be satisfied
+    #[wasm_bindgen(getter)]
+    pub fn threshold(&self) -> Option<usize> {
+        match &self.0.item {
+            SatisfiableItem::Multisig { threshold, .. } | SatisfiableItem::Thresh { threshold, .. } => Some(*threshold),
+            _ => None,
+        }
+    }
+
+    /// Children of a `THRESH` node, empty for any other node
+    #[wasm_bindgen(getter)]
+    pub fn children(&self) -> Vec<Policy> {
+        match &self.0.item {
+            SatisfiableItem::Thresh { items, .. } => items.iter().cloned().map(Into::into).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// How far the wallet's signers can satisfy this node on their own
+    #[wasm_bindgen(getter)]
+    pub fn contribution(&self) -> JsValue {
+        to_js_value(&self.0.contribution)
+    }
+
+    /// How much a PSBT already satisfies this node
+    #[wasm_bindgen(getter)]
+    pub fn satisfaction(&self) -> JsValue {
+        to_js_value(&self.0.satisfaction)
+    }
+
+    /// Return whether or not a specific path in the policy tree is required to unambiguously
+    /// create a transaction.
+    ///
+    /// What this means is that for some spending policies the user should select which paths in
+    /// the tree it intends to satisfy before commiting to a transaction.
+    pub fn requires_path(&self) -> bool {
+        self.0.requires_path()
+    }
+
+    /// Serialize `Policy` to JSON.
+    pub fn to_json(&self) -> String {
+        to_string(&self.0).expect("Serialization should not fail")
+    }
+}
+
+fn to_js_value<T: Serialize>(value: &T) -> JsValue {
+    value
+        .serialize(&Serializer::json_compatible())
+        .expect("Serialization should not fail")
+}
+
+impl Deref for Policy {
+    type Target = BdkPolicy;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<BdkPolicy> for Policy {
+    fn from(inner: BdkPolicy) -> Self {
+        Policy(inner)
+    }
+}
+
+impl From<Policy> for BdkPolicy {
+    fn from(policy: Policy) -> Self {
+        policy.0
+    }
+}
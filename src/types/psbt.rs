@@ -1,9 +1,12 @@
-use bdk_wallet::serde_json::to_string;
+use bdk_wallet::serde_json::{from_str, to_string};
 use std::ops::{Deref, DerefMut};
 use std::str::FromStr;
 
 use bdk_wallet::{
-    bitcoin::{Amount as BdkAmount, Psbt as BdkPsbt, ScriptBuf as BdkScriptBuf},
+    bitcoin::{
+        psbt::{Input as BdkPsbtInput, PsbtSighashType},
+        Amount as BdkAmount, Psbt as BdkPsbt, ScriptBuf as BdkScriptBuf,
+    },
     psbt::PsbtUtils,
 };
 
@@ -12,7 +15,7 @@ use wasm_bindgen::prelude::wasm_bindgen;
 use crate::result::JsResult;
 use crate::types::ScriptBuf;
 
-use super::{Address, Amount, FeeRate, Transaction};
+use super::{Address, Amount, FeeRate, Transaction, TxOut};
 
 /// A Partially Signed Transaction.
 #[wasm_bindgen]
@@ -98,6 +101,12 @@ impl Psbt {
         self.0.unsigned_tx.clone().into()
     }
 
+    /// The corresponding key-value map for each input in the unsigned transaction.
+    #[wasm_bindgen(getter)]
+    pub fn inputs(&self) -> Vec<PsbtInput> {
+        self.0.inputs.iter().cloned().map(Into::into).collect()
+    }
+
     /// Serialize the PSBT to a string in base64 format
     #[allow(clippy::inherent_to_string)]
     #[wasm_bindgen(js_name = toString)]
@@ -128,12 +137,114 @@ impl From<Psbt> for BdkPsbt {
     }
 }
 
+/// A key-value map for an input of the corresponding index in the unsigned transaction.
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct PsbtInput(BdkPsbtInput);
+
+impl Deref for PsbtInput {
+    type Target = BdkPsbtInput;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[wasm_bindgen]
+impl PsbtInput {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        PsbtInput::default()
+    }
+
+    /// The non-witness transaction this input spends from. Should only be
+    /// `Some` for inputs which spend non-segwit outputs or if it is unknown
+    /// whether an input spends a segwit output.
+    #[wasm_bindgen(getter)]
+    pub fn non_witness_utxo(&self) -> Option<Transaction> {
+        self.0.non_witness_utxo.clone().map(Into::into)
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_non_witness_utxo(&mut self, tx: Option<Transaction>) {
+        self.0.non_witness_utxo = tx.map(Into::into);
+    }
+
+    /// The transaction output this input spends from. Should only be `Some` for
+    /// inputs which spend segwit outputs, including P2SH embedded ones.
+    #[wasm_bindgen(getter)]
+    pub fn witness_utxo(&self) -> Option<TxOut> {
+        self.0.witness_utxo.clone().map(Into::into)
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_witness_utxo(&mut self, txout: Option<TxOut>) {
+        self.0.witness_utxo = txout.map(Into::into);
+    }
+
+    /// The sighash type to be used for this input, as its raw PSBT value.
+    #[wasm_bindgen(getter)]
+    pub fn sighash_type(&self) -> Option<u32> {
+        self.0.sighash_type.map(|sighash| sighash.to_u32())
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_sighash_type(&mut self, sighash: Option<u32>) {
+        self.0.sighash_type = sighash.map(PsbtSighashType::from_u32);
+    }
+
+    /// The redeem script for this input.
+    #[wasm_bindgen(getter)]
+    pub fn redeem_script(&self) -> Option<ScriptBuf> {
+        self.0.redeem_script.clone().map(Into::into)
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_redeem_script(&mut self, script: Option<ScriptBuf>) {
+        self.0.redeem_script = script.map(Into::into);
+    }
+
+    /// The witness script for this input.
+    #[wasm_bindgen(getter)]
+    pub fn witness_script(&self) -> Option<ScriptBuf> {
+        self.0.witness_script.clone().map(Into::into)
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_witness_script(&mut self, script: Option<ScriptBuf>) {
+        self.0.witness_script = script.map(Into::into);
+    }
+
+    /// Serialize `PsbtInput` to JSON.
+    pub fn to_json(&self) -> String {
+        to_string(&self.0).expect("Serialization should not fail")
+    }
+
+    /// Create a new `PsbtInput` from a JSON string.
+    pub fn from_json(val: &str) -> JsResult<PsbtInput> {
+        Ok(PsbtInput(from_str(val)?))
+    }
+}
+
+impl From<BdkPsbtInput> for PsbtInput {
+    fn from(inner: BdkPsbtInput) -> Self {
+        PsbtInput(inner)
+    }
+}
+
+impl From<PsbtInput> for BdkPsbtInput {
+    fn from(input: PsbtInput) -> Self {
+        input.0
+    }
+}
+
 /// A Transaction recipient
 #[wasm_bindgen]
 #[derive(Clone)]
 pub struct Recipient {
     script_pubkey: BdkScriptBuf,
     amount: BdkAmount,
+    subtract_fee: bool,
 }
 
 #[wasm_bindgen]
@@ -143,6 +254,7 @@ impl Recipient {
         Recipient {
             script_pubkey: script_pubkey.into(),
             amount: amount.into(),
+            subtract_fee: false,
         }
     }
 
@@ -150,6 +262,7 @@ impl Recipient {
         Recipient {
             script_pubkey: address.script_pubkey().into(),
             amount: amount.into(),
+            subtract_fee: false,
         }
     }
 
@@ -162,6 +275,28 @@ impl Recipient {
     pub fn amount(&self) -> Amount {
         self.amount.into()
     }
+
+    /// Pay the fee of the transaction from this output, its amount is reduced by the fee.
+    ///
+    /// When several recipients pay the fee, it is split evenly between them. The fee can't be subtracted
+    /// when bumping the fee of a transaction or accelerating it with CPFP, building the transaction then
+    /// fails with a `SubtractFeeUnsupported` error.
+    pub fn subtract_fee_from_amount(mut self) -> Self {
+        self.subtract_fee = true;
+        self
+    }
+
+    /// Whether the fee of the transaction is subtracted from the amount of this output
+    #[wasm_bindgen(getter)]
+    pub fn subtracts_fee(&self) -> bool {
+        self.subtract_fee
+    }
+}
+
+impl Recipient {
+    pub(crate) fn set_amount(&mut self, amount: BdkAmount) {
+        self.amount = amount;
+    }
 }
 
 impl From<Recipient> for (BdkScriptBuf, BdkAmount) {
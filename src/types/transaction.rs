@@ -63,6 +63,18 @@ impl Transaction {
         self.0.compute_txid().into()
     }
 
+    /// The protocol version, is currently expected to be 1 or 2 (BIP 68).
+    #[wasm_bindgen(getter)]
+    pub fn version(&self) -> i32 {
+        self.0.version.0
+    }
+
+    /// Block height or timestamp. Transaction cannot be included in a block until this height/time.
+    #[wasm_bindgen(getter)]
+    pub fn lock_time(&self) -> u32 {
+        self.0.lock_time.to_consensus_u32()
+    }
+
     /// List of transaction inputs.
     #[wasm_bindgen(getter)]
     pub fn input(&self) -> Vec<TxIn> {
@@ -0,0 +1,37 @@
+use std::{cell::RefCell, rc::Rc};
+
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::js_sys::Error;
+
+use crate::types::BdkError;
+
+/// Message of a value thrown by a JS function: the message of an `Error`, or the value itself if
+/// it is a string.
+pub(crate) fn js_error_message(error: &JsValue) -> String {
+    match error.dyn_ref::<Error>() {
+        Some(error) => error.message().into(),
+        None => error.as_string().unwrap_or_default(),
+    }
+}
+
+/// Error of a JS callback called by BDK through a trait that can't return it.
+///
+/// The error is kept aside when the callback fails, and must be retrieved with `take` once BDK
+/// returns. Clones share the same error.
+#[derive(Clone, Default)]
+pub(crate) struct ErrorStash(Rc<RefCell<Option<BdkError>>>);
+
+impl ErrorStash {
+    /// Keep `error`, replacing any previous one.
+    pub(crate) fn set(&self, error: BdkError) {
+        self.0.replace(Some(error));
+    }
+
+    pub(crate) fn is_set(&self) -> bool {
+        self.0.borrow().is_some()
+    }
+
+    pub(crate) fn take(&self) -> Option<BdkError> {
+        self.0.borrow_mut().take()
+    }
+}
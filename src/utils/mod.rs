@@ -1,10 +1,12 @@
 mod descriptor;
+mod js_callback;
 
 #[cfg(feature = "debug")]
 mod panic_hook;
 pub mod result;
 
 pub use descriptor::*;
+pub(crate) use js_callback::{js_error_message, ErrorStash};
 
 #[cfg(feature = "debug")]
 pub use panic_hook::set_panic_hook;
@@ -28,8 +28,8 @@ async fn test_browser() {
     let block_height = wallet.latest_checkpoint().height();
     assert_eq!(block_height, 0);
 
-    let sync_request = wallet.start_sync_with_revealed_spks();
-    let update = blockchain_client.sync(sync_request, 1).await.expect("sync");
+    let sync_request = wallet.start_sync_with_revealed_spks(None);
+    let update = blockchain_client.sync(sync_request, 1, None).await.expect("sync");
     wallet.apply_update(update).expect("sync apply_update");
 
     let sync_block_height = wallet.latest_checkpoint().height();
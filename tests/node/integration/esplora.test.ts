@@ -1,15 +1,33 @@
 import {
   Address,
   Amount,
+  BdkError,
+  BdkErrorCode,
+  BlockId,
+  CoinSelection,
+  ConfirmationBlockTime,
   EsploraClient,
+  EsploraClientOptions,
+  EvictedTx,
   FeeRate,
+  KeychainKind,
+  LocalOutput,
   Network,
   Recipient,
+  SendMaxOptions,
   UnconfirmedTx,
   Wallet,
   SignOptions,
   Psbt,
+  PsbtInput,
+  QueryOptions,
+  ScanProgress,
+  Transaction,
+  TxBuilder,
   TxOrdering,
+  TxOut,
+  Txid,
+  UpdateBuilder,
 } from "../../../pkg/bitcoindevkit";
 
 // Tests are expected to run in order
@@ -30,6 +48,7 @@ describe("Esplora client", () => {
 
   let feeRate: FeeRate;
   let wallet: Wallet;
+  let sentTxid: string;
   const esploraClient = new EsploraClient(esploraUrl, 0);
 
   it("creates a new wallet", () => {
@@ -39,6 +58,26 @@ describe("Esplora client", () => {
     );
   });
 
+  it("requires a global timer only to retry or time out requests", () => {
+    const setTimeout = globalThis.setTimeout;
+    try {
+      Reflect.deleteProperty(globalThis, "setTimeout");
+      expect(() => new EsploraClient(esploraUrl, 3)).toThrow(
+        "Retries require a global setTimeout function"
+      );
+      expect(() => new EsploraClient(esploraUrl, 0)).not.toThrow();
+
+      const options = new EsploraClientOptions();
+      options.max_retries = 0;
+      options.timeout = 1000;
+      expect(() => EsploraClient.with_options(esploraUrl, options)).toThrow(
+        "Timeouts require a global setTimeout function"
+      );
+    } finally {
+      globalThis.setTimeout = setTimeout;
+    }
+  });
+
   it("performs full scan on a wallet", async () => {
     const request = wallet.start_full_scan();
     const update = await esploraClient.full_scan(
@@ -52,6 +91,197 @@ describe("Esplora client", () => {
     expect(wallet.latest_checkpoint.height).toBeGreaterThan(0);
   }, 30000);
 
+  it("reports the progress of a scan", async () => {
+    const scanned: ScanProgress[] = [];
+    const restored = Wallet.create(
+      network,
+      externalDescriptor,
+      internalDescriptor
+    );
+    const fullScan = restored.start_full_scan((progress: ScanProgress) =>
+      scanned.push(progress)
+    );
+    restored.apply_update(
+      await esploraClient.full_scan(fullScan, stopGap, parallelRequests)
+    );
+
+    const external = scanned.filter((p) => p.keychain === "external");
+    expect(external.length).toBeGreaterThan(stopGap);
+    expect(external.map((p) => p.index)).toEqual(
+      external.map((_, i) => i)
+    );
+    expect(external.map((p) => p.spks_checked)).toEqual(
+      external.map((_, i) => i + 1)
+    );
+    expect(scanned.every((p) => p.total === undefined)).toBe(true);
+
+    const synced: ScanProgress[] = [];
+    const sync = restored.start_sync_with_revealed_spks(
+      (progress: ScanProgress) => synced.push(progress)
+    );
+    await esploraClient.sync(sync, parallelRequests);
+
+    expect(synced.length).toBeGreaterThan(0);
+    const last = synced[synced.length - 1];
+    expect(
+      last.spks_checked + last.txids_checked + last.outpoints_checked
+    ).toBe(last.total);
+  }, 30000);
+
+  it("cancels a scan", async () => {
+    const controller = new AbortController();
+    const scan = esploraClient.full_scan(
+      wallet.start_full_scan(),
+      stopGap,
+      parallelRequests,
+      controller.signal
+    );
+    controller.abort();
+
+    await expect(scan).rejects.toThrow("was aborted");
+  });
+
+  it("syncs only the chosen scripts, transactions and outputs", async () => {
+    const balance = wallet.balance.total.to_sat();
+    const unspent = wallet.list_unspent();
+    const synced: ScanProgress[] = [];
+    const request = wallet
+      .build_sync_request()
+      .unspent()
+      .unconfirmed()
+      .spks([wallet.peek_address("external", 0).address.script_pubkey])
+      .progress((progress: ScanProgress) => synced.push(progress))
+      .build();
+
+    wallet.apply_update(await esploraClient.sync(request, parallelRequests));
+
+    expect(wallet.balance.total.to_sat()).toBe(balance);
+    expect(synced[synced.length - 1].total).toBeGreaterThanOrEqual(
+      unspent.length + 1
+    );
+    expect(synced.filter((p) => p.outpoint !== undefined)).toHaveLength(
+      unspent.length
+    );
+    expect(() =>
+      wallet
+        .build_sync_request()
+        .spks([recipientAddress.script_pubkey])
+        .build()
+    ).toThrow("is not derived by the wallet");
+  }, 30000);
+
+  it("scans each keychain with its own stop gap", async () => {
+    const restored = Wallet.create(
+      network,
+      externalDescriptor,
+      internalDescriptor
+    );
+    const internalStopGap = 1;
+    const scanned: ScanProgress[] = [];
+    const request = restored
+      .build_full_scan_request()
+      .stop_gap("internal", internalStopGap)
+      .progress((progress: ScanProgress) => scanned.push(progress))
+      .build();
+    expect(request.stop_gap("internal")).toBe(internalStopGap);
+    expect(request.stop_gap("external")).toBeUndefined();
+
+    // One request at a time, so that each keychain is scanned exactly up to its stop gap
+    const scanParallelism = 1;
+    restored.apply_update(
+      await esploraClient.full_scan(request, stopGap, scanParallelism)
+    );
+    expect(restored.balance.total.to_sat()).toBeGreaterThan(0);
+
+    const lastScanned = (keychain: KeychainKind) =>
+      Math.max(
+        ...scanned.filter((p) => p.keychain === keychain).map((p) => p.index)
+      );
+    const lastActive = (keychain: KeychainKind) =>
+      restored.derivation_index(keychain) ?? -1;
+    expect(lastScanned("internal")).toBeLessThanOrEqual(
+      lastActive("internal") + internalStopGap + scanParallelism - 1
+    );
+    expect(lastScanned("external")).toBeGreaterThanOrEqual(
+      lastActive("external") + stopGap
+    );
+  }, 60000);
+
+  it("applies an update built from a custom chain source", () => {
+    const restored = Wallet.create(
+      network,
+      externalDescriptor,
+      internalDescriptor
+    );
+
+    const fullScan = restored.start_full_scan();
+    expect(fullScan.keychains).toEqual(["external", "internal"]);
+    expect(fullScan.next_spk("external").index).toBe(0);
+
+    const sync = wallet.start_sync_with_revealed_spks();
+    const spks = sync.take_spks();
+    expect(spks.length).toBeGreaterThan(0);
+    expect(sync.take_spks()).toHaveLength(0);
+
+    const confirmed = wallet
+      .transactions()
+      .find((tx) => tx.chain_position.is_confirmed);
+    const anchor = confirmed.chain_position.anchor;
+    const genesis = restored.latest_checkpoint;
+    const tip = wallet.latest_checkpoint;
+    // The chain of the update must connect to the genesis block of the wallet
+    const update = new UpdateBuilder()
+      .add_block(new BlockId(genesis.height, genesis.hash))
+      .add_tx(confirmed.tx)
+      .add_anchor(
+        confirmed.txid,
+        new ConfirmationBlockTime(
+          new BlockId(anchor.block_id.height, anchor.block_id.hash),
+          anchor.confirmation_time
+        )
+      )
+      .add_block(new BlockId(tip.height, tip.hash))
+      .build();
+
+    restored.apply_update(update);
+
+    expect(restored.latest_checkpoint.height).toBe(tip.height);
+    const applied = restored.get_tx(confirmed.txid);
+    expect(applied.chain_position.is_confirmed).toBe(true);
+    expect(applied.chain_position.anchor.block_id.height).toBe(
+      anchor.block_id.height
+    );
+    expect(() => new BlockId(0, "not a hash")).toThrow();
+  });
+
+  it("reports the events of an update", async () => {
+    const restored = Wallet.create(
+      network,
+      externalDescriptor,
+      internalDescriptor
+    );
+    const update = await esploraClient.full_scan(
+      restored.start_full_scan(),
+      stopGap,
+      parallelRequests
+    );
+    const events = restored.apply_update_events(update);
+    const kinds = events.map((event) => event.kind);
+
+    expect(kinds).toContain("address_used");
+    expect(kinds).toContain("tx_received");
+    expect(kinds).toContain("tx_confirmed");
+    expect(kinds[kinds.length - 1]).toBe("balance_changed");
+    expect(events[events.length - 1].balance.total.to_sat()).toBe(
+      restored.balance.total.to_sat()
+    );
+
+    const received = events.filter((event) => event.kind === "tx_received");
+    for (const event of received) {
+      expect(restored.get_tx(event.txid)).toBeDefined();
+    }
+  }, 30000);
+
   it("fetches fee estimates", async () => {
     const confirmationTarget = 2;
     const feeEstimates = await esploraClient.get_fee_estimates();
@@ -61,6 +291,79 @@ describe("Esplora client", () => {
     feeRate = new FeeRate(BigInt(Math.floor(fee)));
   });
 
+  it("sends requests through an injected fetch", async () => {
+    const requests: { url: string; headers: Record<string, string> }[] = [];
+    const options = new EsploraClientOptions();
+    options.set_header("X-Api-Key", "secret");
+    options.fetch = (url: string, init: RequestInit) => {
+      requests.push({ url, headers: init.headers as Record<string, string> });
+      return fetch(url, init);
+    };
+    const client = EsploraClient.with_options(esploraUrl, options);
+
+    const feeEstimates = await client.get_fee_estimates();
+
+    expect(feeEstimates.get(2)).toBeDefined();
+    expect(requests).toHaveLength(1);
+    expect(requests[0].url).toBe(`${esploraUrl}/fee-estimates`);
+    expect(requests[0].headers["X-Api-Key"]).toBe("secret");
+  });
+
+  it("fails to scan a wallet of another network", async () => {
+    const testnetWallet = Wallet.create(
+      "testnet",
+      externalDescriptor,
+      internalDescriptor
+    );
+
+    await expect(
+      esploraClient.full_scan(
+        testnetWallet.start_full_scan(),
+        stopGap,
+        parallelRequests
+      )
+    ).rejects.toThrow("different genesis block");
+    await expect(
+      esploraClient.sync(
+        testnetWallet.start_sync_with_revealed_spks(),
+        parallelRequests
+      )
+    ).rejects.toThrow("different genesis block");
+  });
+
+  it("aborts requests on timeout or signal", async () => {
+    // Never answers, only rejects once the request is aborted.
+    const hangingFetch = (_url: string, init: RequestInit) =>
+      new Promise((_resolve, reject) => {
+        init.signal?.addEventListener("abort", () =>
+          reject(new Error("aborted"))
+        );
+      });
+
+    const timeoutOptions = new EsploraClientOptions();
+    timeoutOptions.fetch = hangingFetch;
+    timeoutOptions.timeout = 50;
+    const timeoutClient = EsploraClient.with_options(
+      esploraUrl,
+      timeoutOptions
+    );
+    await expect(timeoutClient.get_fee_estimates()).rejects.toThrow(
+      "timed out after 50 ms"
+    );
+
+    const controller = new AbortController();
+    const signalOptions = new EsploraClientOptions();
+    signalOptions.fetch = hangingFetch;
+    signalOptions.signal = controller.signal;
+    const signalClient = EsploraClient.with_options(esploraUrl, signalOptions);
+    const pending = signalClient.get_fee_estimates();
+    controller.abort();
+    await expect(pending).rejects.toThrow("was aborted");
+    await expect(signalClient.get_fee_estimates()).rejects.toThrow(
+      "was aborted"
+    );
+  });
+
   it("sends a transaction", async () => {
     const sendAmount = Amount.from_sat(BigInt(1000));
     expect(wallet.balance.trusted_spendable.to_sat()).toBeGreaterThan(
@@ -82,6 +385,7 @@ describe("Esplora client", () => {
     const tx = psbt.extract_tx();
     const txid = tx.compute_txid();
     await esploraClient.broadcast(tx);
+    sentTxid = txid.toString();
 
     // Assert that we are aware of newly created addresses that were revealed during PSBT creation
     const currentDerivationIndex = wallet.derivation_index("internal");
@@ -94,6 +398,178 @@ describe("Esplora client", () => {
     expect(walletTx.chain_position.is_confirmed).toBe(false);
   }, 30000);
 
+  it("bumps the fee of an unconfirmed transaction", () => {
+    const unconfirmed = wallet.get_tx(Txid.from_string(sentTxid));
+    expect(unconfirmed.chain_position.is_confirmed).toBe(false);
+
+    const originalFee = wallet.calculate_fee(unconfirmed.tx);
+    const psbt = wallet
+      .build_fee_bump(unconfirmed.txid)
+      .fee_rate(new FeeRate(feeRate.to_sat_per_vb_ceil() + BigInt(1)))
+      .finish();
+
+    const inputs = (tx: Transaction) =>
+      tx.input.map((txin) => txin.previous_output.toString());
+
+    expect(psbt.fee().to_sat()).toBeGreaterThan(originalFee.to_sat());
+    expect(inputs(psbt.unsigned_tx)).toEqual(
+      expect.arrayContaining(inputs(unconfirmed.tx))
+    );
+
+    const preview = wallet
+      .build_fee_bump(unconfirmed.txid)
+      .add_recipient(
+        new Recipient(
+          recipientAddress.script_pubkey,
+          Amount.from_sat(BigInt(1000))
+        ).subtract_fee_from_amount()
+      )
+      .preview();
+    expect(preview.error.code).toBe(BdkErrorCode.SubtractFeeUnsupported);
+  });
+
+  it("accelerates an unconfirmed transaction with CPFP", () => {
+    const parent = wallet.get_tx(Txid.from_string(sentTxid));
+    const targetFeeRate = new FeeRate(
+      feeRate.to_sat_per_vb_ceil() + BigInt(5)
+    );
+
+    const psbt = wallet
+      .build_cpfp(parent.txid)
+      .fee_rate(targetFeeRate)
+      .finish();
+
+    const child = psbt.unsigned_tx;
+    expect(
+      child.input.every(
+        (txin) => txin.previous_output.txid.toString() === sentTxid
+      )
+    ).toBe(true);
+    expect(child.output).toHaveLength(1);
+    expect(wallet.is_mine(child.output[0].script_pubkey)).toBe(true);
+
+    // The child pays for the missing fee of its parent, measured on its signed size
+    const parentFee = wallet.calculate_fee(parent.tx).to_sat();
+    const childFee = psbt.fee().to_sat();
+    wallet.sign(psbt, new SignOptions());
+    const signed = psbt.extract_tx();
+    const packageVsize = BigInt(parent.tx.vsize + signed.vsize);
+    expect(parentFee + childFee).toBeGreaterThanOrEqual(
+      packageVsize * targetFeeRate.to_sat_per_vb_floor()
+    );
+  });
+
+  it("lists the transaction history", () => {
+    const history = wallet.history();
+    expect(history).toHaveLength(wallet.transactions().length);
+
+    // Unconfirmed transactions come first
+    const sent = history[0];
+    expect(sent.txid.toString()).toBe(sentTxid);
+    expect(sent.direction).toBe("outgoing");
+    expect(sent.confirmations).toBe(0);
+    expect(sent.net).toBe(sent.received.to_sat() - sent.sent.to_sat());
+    expect(sent.fee.to_sat()).toBe(
+      wallet.calculate_fee(sent.tx).to_sat()
+    );
+    expect(sent.fee_rate).toBeDefined();
+    expect(sent.addresses.length).toBeGreaterThan(0);
+
+    const confirmed = history.filter((tx) => tx.chain_position.is_confirmed);
+    expect(confirmed.length).toBeGreaterThan(0);
+    for (const tx of confirmed) {
+      expect(tx.confirmations).toBe(
+        wallet.latest_checkpoint.height -
+          tx.chain_position.anchor.block_id.height +
+          1
+      );
+    }
+
+    const details = wallet.tx_details(Txid.from_string(sentTxid));
+    expect(details.net).toBe(sent.net);
+  });
+
+  it("queries transactions and outputs by page", () => {
+    const history = wallet.history();
+
+    const options = new QueryOptions();
+    options.limit = 1;
+    expect(
+      wallet.query_transactions(options).map((tx) => tx.txid.toString())
+    ).toEqual([history[0].txid.toString()]);
+
+    options.offset = 1;
+    options.limit = 2;
+    expect(
+      wallet.query_transactions(options).map((tx) => tx.txid.toString())
+    ).toEqual(history.slice(1, 3).map((tx) => tx.txid.toString()));
+
+    const confirmedOnly = new QueryOptions();
+    confirmedOnly.confirmed = true;
+    confirmedOnly.descending = false;
+    const confirmed = wallet.query_transactions(confirmedOnly);
+    expect(confirmed.every((tx) => tx.chain_position.is_confirmed)).toBe(true);
+    const heights = confirmed.map(
+      (tx) => tx.chain_position.anchor.block_id.height
+    );
+    expect(heights).toEqual([...heights].sort((a, b) => a - b));
+
+    const changeOnly = new QueryOptions();
+    changeOnly.keychain = "internal";
+    changeOnly.min_value = Amount.from_sat(BigInt(1000));
+    const change = wallet.query_unspent(changeOnly);
+    expect(
+      change.every(
+        (utxo) =>
+          utxo.keychain === "internal" && utxo.txout.value.to_sat() >= 1000
+      )
+    ).toBe(true);
+    expect(change.length).toBeLessThanOrEqual(wallet.list_unspent().length);
+
+    const recent = new QueryOptions();
+    recent.sort = "first_seen";
+    recent.from_time = unixTimestamp;
+    expect(
+      wallet.query_outputs(recent).every(
+        (output) => !output.chain_position.is_confirmed
+      )
+    ).toBe(true);
+  });
+
+  it("breaks down the balance by keychain, address and height", () => {
+    const total = wallet.balance.total.to_sat();
+    const external = wallet.keychain_balance("external").total.to_sat();
+    const internal = wallet.keychain_balance("internal").total.to_sat();
+    expect(external + internal).toBe(total);
+
+    const addressBalances = wallet.address_balances("external");
+    expect(addressBalances).toHaveLength(
+      wallet.derivation_index("external") + 1
+    );
+    expect(
+      addressBalances.reduce(
+        (sum, entry) => sum + entry.balance.total.to_sat(),
+        BigInt(0)
+      )
+    ).toBe(external);
+    const first = addressBalances[0];
+    expect(first.address_info.index).toBe(0);
+    expect(wallet.address_balance("external", 0).total.to_sat()).toBe(
+      first.balance.total.to_sat()
+    );
+
+    const history = wallet.balance_history();
+    expect(history.length).toBeGreaterThan(0);
+    const last = history[history.length - 1];
+    expect(
+      wallet.balance_at_height(wallet.latest_checkpoint.height).to_sat()
+    ).toBe(last.balance.to_sat());
+    expect(wallet.balance_at_height(history[0].height - 1).to_sat()).toBe(
+      BigInt(0)
+    );
+    expect(wallet.balance_at_time(BigInt(0)).to_sat()).toBe(BigInt(0));
+  });
+
   it("excludes utxos from a transaction", () => {
     const utxos = wallet.list_unspent();
     expect(utxos.length).toBeGreaterThan(0);
@@ -108,6 +584,495 @@ describe("Esplora client", () => {
     }).toThrow();
   });
 
+  it("spends only manually selected utxos", () => {
+    const utxo = wallet.list_unspent()[0];
+    const outpoint = utxo.outpoint.toString();
+
+    const psbt = wallet
+      .build_tx()
+      .add_utxo(utxo.outpoint)
+      .manually_selected_only()
+      .drain_to(recipientAddress.script_pubkey)
+      .finish();
+
+    const inputs = psbt.unsigned_tx.input;
+    expect(inputs).toHaveLength(1);
+    expect(inputs[0].previous_output.toString()).toBe(outpoint);
+  });
+
+  it("spends a foreign utxo", () => {
+    // Our own utxo is used as a foreign one so we don't depend on a second funded wallet
+    const utxo = wallet.list_unspent()[0];
+    const input = new PsbtInput();
+    input.witness_utxo = utxo.txout;
+    input.non_witness_utxo = wallet.get_tx(utxo.outpoint.txid).tx;
+
+    const psbt = wallet
+      .build_tx()
+      .add_foreign_utxo(utxo.outpoint, input, BigInt(108))
+      .manually_selected_only()
+      .drain_to(recipientAddress.script_pubkey)
+      .finish();
+
+    const inputs = psbt.unsigned_tx.input;
+    expect(inputs).toHaveLength(1);
+    expect(inputs[0].previous_output.toString()).toBe(
+      utxo.outpoint.toString()
+    );
+    expect(psbt.inputs[0].witness_utxo.value.to_sat()).toBe(
+      utxo.txout.value.to_sat()
+    );
+  });
+
+  it("selects coins with the chosen algorithm", () => {
+    const sendAmount = Amount.from_sat(BigInt(1000));
+    const utxos = wallet.list_unspent();
+    const largest = utxos
+      .reduce((max, utxo) =>
+        utxo.txout.value.to_sat() > max.txout.value.to_sat() ? utxo : max
+      )
+      .outpoint.toString();
+
+    const psbt = wallet
+      .build_tx()
+      .coin_selection(CoinSelection.LargestFirst)
+      .add_recipient(new Recipient(recipientAddress.script_pubkey, sendAmount))
+      .finish();
+    expect(psbt.unsigned_tx.input).toHaveLength(1);
+    expect(psbt.unsigned_tx.input[0].previous_output.toString()).toBe(
+      largest
+    );
+
+    const customPsbt = wallet
+      .build_tx()
+      .coin_selection_custom(
+        (candidates: LocalOutput[], target: Amount, feeRate: FeeRate) => {
+          expect(target.to_sat()).toBeGreaterThan(sendAmount.to_sat());
+          expect(feeRate.to_sat_per_vb_ceil()).toBe(BigInt(1));
+          return candidates
+            .filter((utxo) => utxo.outpoint.toString() === largest)
+            .map((utxo) => utxo.outpoint);
+        }
+      )
+      .add_recipient(new Recipient(recipientAddress.script_pubkey, sendAmount))
+      .finish();
+    expect(customPsbt.unsigned_tx.input).toHaveLength(1);
+    expect(
+      customPsbt.unsigned_tx.input[0].previous_output.toString()
+    ).toBe(largest);
+  });
+
+  it("adds OP_RETURN data to a transaction", () => {
+    const data = new TextEncoder().encode("bdk-wasm");
+    const psbt = wallet
+      .build_tx()
+      .add_data(data)
+      .add_recipient(
+        new Recipient(recipientAddress.script_pubkey, Amount.from_sat(BigInt(1000)))
+      )
+      .finish();
+
+    const opReturn = psbt.unsigned_tx.output.find((txout) =>
+      txout.script_pubkey.is_op_return()
+    );
+    expect(opReturn.value.to_sat()).toBe(BigInt(0));
+    expect(opReturn.script_pubkey.as_bytes().slice(2)).toEqual(data);
+  });
+
+  it("sets the locktime, version and sequence of a transaction", () => {
+    const psbt = wallet
+      .build_tx()
+      .nlocktime(100)
+      .version(3)
+      .disable_rbf()
+      .add_recipient(
+        new Recipient(recipientAddress.script_pubkey, Amount.from_sat(BigInt(1000)))
+      )
+      .finish();
+
+    const tx = psbt.unsigned_tx;
+    expect(tx.lock_time).toBe(100);
+    expect(tx.version).toBe(3);
+    expect(tx.is_explicitly_rbf).toBe(false);
+    expect(tx.is_lock_time_enabled).toBe(true);
+    tx.input.forEach((txin) => expect(txin.sequence).toBe(0xfffffffe));
+
+    const outpoint = wallet.list_unspent()[0].outpoint;
+    const sequencedOutpoint = outpoint.toString();
+    const sequenced = wallet
+      .build_tx()
+      .disable_rbf()
+      .add_utxo_with_sequence(outpoint, 10)
+      .add_recipient(
+        new Recipient(recipientAddress.script_pubkey, Amount.from_sat(BigInt(1000)))
+      )
+      .finish().unsigned_tx;
+    sequenced.input.forEach((txin) =>
+      expect(txin.sequence).toBe(
+        txin.previous_output.toString() === sequencedOutpoint ? 10 : 0xfffffffe
+      )
+    );
+    expect(sequenced.is_explicitly_rbf).toBe(true);
+  });
+
+  it("subtracts the fee from the amount of a recipient", () => {
+    const amount = BigInt(10000);
+    const recipientOutput = (psbt: Psbt) =>
+      psbt.unsigned_tx.output.find(
+        (txout) =>
+          txout.script_pubkey.as_bytes().toString() ===
+          recipientAddress.script_pubkey.as_bytes().toString()
+      );
+
+    let psbt = wallet
+      .build_tx()
+      .fee_absolute(Amount.from_sat(BigInt(500)))
+      .add_recipient(
+        new Recipient(
+          recipientAddress.script_pubkey,
+          Amount.from_sat(amount)
+        ).subtract_fee_from_amount()
+      )
+      .finish();
+    expect(psbt.fee().to_sat()).toBe(BigInt(500));
+    expect(recipientOutput(psbt).value.to_sat()).toBe(amount - BigInt(500));
+
+    psbt = wallet
+      .build_tx()
+      .fee_rate(new FeeRate(BigInt(2)))
+      .add_recipient(
+        new Recipient(
+          recipientAddress.script_pubkey,
+          Amount.from_sat(amount)
+        ).subtract_fee_from_amount()
+      )
+      .finish();
+    expect(recipientOutput(psbt).value.to_sat() + psbt.fee().to_sat()).toBe(
+      amount
+    );
+  });
+
+  it("drops a dust change when subtracting the fee", () => {
+    const utxo = wallet
+      .list_unspent()
+      .find((utxo) => utxo.txout.value.to_sat() > BigInt(5000));
+    const value = utxo.txout.value.to_sat();
+
+    // The 200 sats left after the recipient are below the dust limit of a change output
+    const psbt = wallet
+      .build_tx()
+      .add_utxo(utxo.outpoint)
+      .manually_selected_only()
+      .add_recipient(
+        new Recipient(
+          recipientAddress.script_pubkey,
+          Amount.from_sat(value - BigInt(200))
+        ).subtract_fee_from_amount()
+      )
+      .finish();
+
+    const outputs = psbt.unsigned_tx.output;
+    expect(outputs).toHaveLength(1);
+    expect(outputs[0].value.to_sat()).toBeLessThan(value - BigInt(200));
+    expect(outputs[0].value.to_sat() + psbt.fee().to_sat()).toBe(value);
+  });
+
+  it("splits the fee between the recipients paying it", () => {
+    const amount = BigInt(10000);
+    const psbt = wallet
+      .build_tx()
+      .ordering(TxOrdering.Untouched)
+      .fee_absolute(Amount.from_sat(BigInt(501)))
+      .add_recipient(
+        new Recipient(
+          recipientAddress.script_pubkey,
+          Amount.from_sat(amount)
+        ).subtract_fee_from_amount()
+      )
+      .add_recipient(
+        new Recipient(
+          recipientAddress.script_pubkey,
+          Amount.from_sat(amount)
+        ).subtract_fee_from_amount()
+      )
+      .finish();
+
+    // The satoshi left over by the split is paid by the first recipient
+    const [first, second] = psbt.unsigned_tx.output;
+    expect(psbt.fee().to_sat()).toBe(BigInt(501));
+    expect(first.value.to_sat()).toBe(amount - BigInt(251));
+    expect(second.value.to_sat()).toBe(amount - BigInt(250));
+  });
+
+  it("fails to subtract the fee below the dust limit", () => {
+    const preview = wallet
+      .build_tx()
+      .fee_absolute(Amount.from_sat(BigInt(500)))
+      .add_recipient(
+        new Recipient(
+          recipientAddress.script_pubkey,
+          Amount.from_sat(BigInt(600))
+        ).subtract_fee_from_amount()
+      )
+      .preview();
+    expect(preview.error.code).toBe(BdkErrorCode.OutputBelowDustLimit);
+  });
+
+  it("fills the PSBT fields required by hardware signers", () => {
+    const recipient = () =>
+      new Recipient(recipientAddress.script_pubkey, Amount.from_sat(BigInt(1000)));
+    const xpub = wallet
+      .public_descriptor("external")
+      .match(/\]([a-zA-Z0-9]+)\//)[1];
+
+    let psbt = wallet
+      .build_tx()
+      .add_global_xpubs()
+      .sighash(0x01)
+      .add_recipient(recipient())
+      .finish();
+    expect(psbt.to_json()).toContain(xpub);
+    psbt.inputs.forEach((input) => {
+      expect(input.non_witness_utxo).toBeDefined();
+      expect(input.sighash_type).toBe(0x01);
+    });
+
+    psbt = wallet
+      .build_tx()
+      .only_witness_utxo()
+      .add_recipient(recipient())
+      .finish();
+    expect(psbt.to_json()).not.toContain(xpub);
+    psbt.inputs.forEach((input) => {
+      expect(input.non_witness_utxo).toBeUndefined();
+      expect(input.witness_utxo).toBeDefined();
+      expect(input.sighash_type).toBeUndefined();
+    });
+  });
+
+  it("orders inputs and outputs", () => {
+    expect.assertions(5);
+
+    const recipients = () => [
+      new Recipient(recipientAddress.script_pubkey, Amount.from_sat(BigInt(3000))),
+      new Recipient(recipientAddress.script_pubkey, Amount.from_sat(BigInt(1000))),
+      new Recipient(recipientAddress.script_pubkey, Amount.from_sat(BigInt(2000))),
+    ];
+    const values = (psbt: Psbt) =>
+      psbt.unsigned_tx.output.map((txout) => txout.value.to_sat());
+    const ascending = (a: bigint, b: bigint) => (a < b ? -1 : a > b ? 1 : 0);
+
+    let psbt = wallet
+      .build_tx()
+      .set_recipients(recipients())
+      .ordering(TxOrdering.Bip69)
+      .finish();
+    expect(values(psbt)).toEqual([...values(psbt)].sort(ascending));
+
+    psbt = wallet
+      .build_tx()
+      .set_recipients(recipients())
+      .ordering_custom(
+        () => 0,
+        (a: TxOut, b: TxOut) => ascending(b.value.to_sat(), a.value.to_sat())
+      )
+      .finish();
+    expect(values(psbt)).toEqual(
+      [...values(psbt)].sort((a, b) => ascending(b, a))
+    );
+
+    try {
+      wallet
+        .build_tx()
+        .set_recipients(recipients())
+        .ordering_custom(
+          () => 0,
+          () => {
+            throw new Error("cannot compare");
+          }
+        )
+        .finish();
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect(error.code).toBe(BdkErrorCode.OrderingFailed);
+      expect(error.message).toBe("cannot compare");
+    }
+  });
+
+  it("builds reproducible transactions from a seed", () => {
+    expect.assertions(4);
+
+    const build = (builder: TxBuilder) =>
+      builder
+        .coin_selection(CoinSelection.SingleRandomDraw)
+        .ordering(TxOrdering.Shuffle)
+        .add_recipient(
+          new Recipient(recipientAddress.script_pubkey, Amount.from_sat(BigInt(1000)))
+        )
+        .drain_to(recipientAddress.script_pubkey)
+        .finish()
+        .toString();
+
+    expect(build(wallet.build_tx().seed(BigInt(42)))).toBe(
+      build(wallet.build_tx().seed(BigInt(42)))
+    );
+
+    const randomBytes = (length: number) => new Uint8Array(length).fill(7);
+    expect(build(wallet.build_tx().random_bytes(randomBytes))).toBe(
+      build(wallet.build_tx().random_bytes(randomBytes))
+    );
+
+    try {
+      build(wallet.build_tx().random_bytes(() => new Uint8Array(1)));
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect(error.code).toBe(BdkErrorCode.RandomnessFailed);
+    }
+  });
+
+  it("previews a transaction without changing the wallet", () => {
+    const builder = () =>
+      wallet
+        .build_tx()
+        .coin_selection(CoinSelection.LargestFirst)
+        .fee_rate(new FeeRate(BigInt(2)))
+        .add_recipient(
+          new Recipient(recipientAddress.script_pubkey, Amount.from_sat(BigInt(1000)))
+        );
+    const changeIndex = wallet.derivation_index("internal");
+
+    const preview = builder().preview();
+    expect(preview.error).toBeUndefined();
+    expect(preview.inputs.length).toBeGreaterThan(0);
+    expect(preview.vsize).toBeGreaterThan(BigInt(100));
+    expect(wallet.derivation_index("internal")).toBe(changeIndex);
+
+    const psbt = builder().finish();
+    expect(preview.fee.to_sat()).toBe(psbt.fee().to_sat());
+    expect(preview.inputs.map((outpoint) => outpoint.toString())).toEqual(
+      psbt.unsigned_tx.input.map((txin) => txin.previous_output.toString())
+    );
+
+    const tooLarge = wallet
+      .build_tx()
+      .add_recipient(
+        new Recipient(
+          recipientAddress.script_pubkey,
+          Amount.from_sat(BigInt(2_100_000_000_000_000))
+        )
+      )
+      .preview();
+    expect(tooLarge.fee).toBeUndefined();
+    expect(tooLarge.error.code).toBe(BdkErrorCode.InsufficientFunds);
+  });
+
+  it("computes the maximum amount sendable", () => {
+    const feeRate = new FeeRate(BigInt(2));
+    const max = wallet.max_sendable(
+      recipientAddress.script_pubkey,
+      feeRate,
+      new SendMaxOptions()
+    );
+    expect(max.to_sat()).toBeGreaterThan(BigInt(0));
+
+    const psbt = wallet
+      .build_tx()
+      .fee_rate(new FeeRate(BigInt(2)))
+      .drain_wallet()
+      .add_recipient(new Recipient(recipientAddress.script_pubkey, max))
+      .preview();
+    expect(psbt.error).toBeUndefined();
+    expect(psbt.change_amount).toBeUndefined();
+
+    const options = new SendMaxOptions();
+    options.recipients = [
+      new Recipient(recipientAddress.script_pubkey, Amount.from_sat(BigInt(1000))),
+    ];
+    options.min_confirmations = 1;
+    const maxWithRecipient = wallet.max_sendable(
+      recipientAddress.script_pubkey,
+      new FeeRate(BigInt(2)),
+      options
+    );
+    expect(maxWithRecipient.to_sat()).toBeLessThan(max.to_sat() - BigInt(1000));
+  });
+
+  it("abandons an unbroadcast transaction", () => {
+    const balance = wallet.balance.total.to_sat();
+    const psbt = wallet
+      .build_tx()
+      .fee_rate(new FeeRate(BigInt(2)))
+      .add_recipient(
+        new Recipient(recipientAddress.script_pubkey, Amount.from_sat(BigInt(1000)))
+      )
+      .finish();
+    wallet.sign(psbt, new SignOptions());
+    const tx = psbt.extract_tx();
+    const txid = tx.compute_txid().toString();
+
+    wallet.apply_unconfirmed_txs([new UnconfirmedTx(tx, unixTimestamp)]);
+    expect(wallet.get_tx(Txid.from_string(txid))).toBeDefined();
+    expect(wallet.balance.total.to_sat()).toBeLessThan(balance);
+
+    wallet.abandon_tx(Txid.from_string(txid));
+    expect(wallet.get_tx(Txid.from_string(txid))).toBeUndefined();
+    expect(wallet.balance.total.to_sat()).toBe(balance);
+  });
+
+  it("evicts a transaction dropped from the mempool", () => {
+    const balance = wallet.balance.trusted_pending.to_sat();
+    const psbt = wallet
+      .build_tx()
+      .fee_rate(new FeeRate(BigInt(2)))
+      .add_recipient(
+        new Recipient(recipientAddress.script_pubkey, Amount.from_sat(BigInt(1000)))
+      )
+      .finish();
+    wallet.sign(psbt, new SignOptions());
+    const tx = psbt.extract_tx();
+    const txid = tx.compute_txid().toString();
+
+    wallet.apply_unconfirmed_txs([new UnconfirmedTx(tx, unixTimestamp)]);
+    expect(wallet.balance.trusted_pending.to_sat()).toBeGreaterThan(balance);
+
+    // An eviction older than the last time the transaction was seen is ignored
+    wallet.apply_evicted_txs([
+      new EvictedTx(Txid.from_string(txid), unixTimestamp - BigInt(1)),
+    ]);
+    expect(wallet.get_tx(Txid.from_string(txid))).toBeDefined();
+
+    wallet.apply_evicted_txs([
+      new EvictedTx(Txid.from_string(txid), unixTimestamp + BigInt(1)),
+    ]);
+    expect(wallet.get_tx(Txid.from_string(txid))).toBeUndefined();
+    expect(wallet.balance.trusted_pending.to_sat()).toBe(balance);
+
+    const evicted = wallet
+      .non_canonical_txs()
+      .find((nonCanonical) => nonCanonical.txid.toString() === txid);
+    expect(evicted).toBeDefined();
+    expect(evicted.last_seen).toBe(unixTimestamp);
+    expect(evicted.is_replaced).toBe(false);
+    expect(evicted.replaced_by).toHaveLength(0);
+  });
+
+  it("frees the change address of a cancelled transaction", () => {
+    const psbt = wallet
+      .build_tx()
+      .fee_rate(new FeeRate(BigInt(2)))
+      .add_recipient(
+        new Recipient(recipientAddress.script_pubkey, Amount.from_sat(BigInt(1000)))
+      )
+      .finish();
+    const changeIndex = wallet.derivation_index("internal");
+    const change = wallet.peek_address("internal", changeIndex);
+
+    wallet.cancel_tx(psbt.unsigned_tx);
+    expect(wallet.next_unused_address("internal").address.toString()).toBe(
+      change.address.toString()
+    );
+  });
+
   it("fills inputs of an output-only Psbt", () => {
     const psbtBase64 =
       "cHNidP8BAI4CAAAAAAM1gwEAAAAAACJRIORP1Ndiq325lSC/jMG0RlhATHYmuuULfXgEHUM3u5i4AAAAAAAAAAAxai8AAUSx+i9Igg4HWdcpyagCs8mzuRCklgA7nRMkm69rAAAAAAAAAAAAAQACAAAAACp2AAAAAAAAFgAUArpyBMj+3+/wQDj+orDWG4y4yfUAAAAAAAAAAAA=";
@@ -3,8 +3,18 @@ import {
   Amount,
   BdkError,
   BdkErrorCode,
+  BlockId,
+  ConfirmationBlockTime,
   FeeRate,
+  OutPoint,
+  Psbt,
+  PsbtInput,
   Recipient,
+  ScriptBuf,
+  SendMaxOptions,
+  Transaction,
+  Txid,
+  UpdateBuilder,
   Wallet,
 } from "../../../pkg/bitcoindevkit";
 import type { Network } from "../../../pkg/bitcoindevkit";
@@ -21,6 +31,36 @@ describe("Wallet", () => {
     network
   );
 
+  // Transaction paying `value` to `script`, decoded from a PSBT holding it
+  const fundingTx = (script: ScriptBuf, value: bigint): Transaction => {
+    const scriptBytes = script.as_bytes();
+    const u32le = (n: number) => {
+      const bytes = new Uint8Array(4);
+      new DataView(bytes.buffer).setUint32(0, n, true);
+      return [...bytes];
+    };
+    const u64le = (n: bigint) => {
+      const bytes = new Uint8Array(8);
+      new DataView(bytes.buffer).setBigUint64(0, n, true);
+      return [...bytes];
+    };
+    const tx = [
+      ...u32le(2),
+      1,
+      ...new Array(32).fill(1),
+      ...u32le(0),
+      0,
+      ...u32le(0xffffffff),
+      1,
+      ...u64le(value),
+      scriptBytes.length,
+      ...scriptBytes,
+      ...u32le(0),
+    ];
+    const psbt = [0x70, 0x73, 0x62, 0x74, 0xff, 1, 0, tx.length, ...tx, 0, 0, 0];
+    return Psbt.from_string(Buffer.from(psbt).toString("base64")).unsigned_tx;
+  };
+
   it("creates a new wallet from descriptors", () => {
     wallet = Wallet.create(network, externalDesc, internalDesc);
 
@@ -77,4 +117,341 @@ describe("Wallet", () => {
       expect(data.available).toBeDefined();
     }
   });
+
+  it("fails to bump the fee of an unknown transaction", () => {
+    expect.assertions(3);
+
+    const txid =
+      "0000000000000000000000000000000000000000000000000000000000000001";
+
+    try {
+      wallet.build_fee_bump(Txid.from_string(txid));
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+
+      const { code, data } = error;
+      expect(code).toBe(BdkErrorCode.TransactionNotFound);
+      expect(data).toBe(txid);
+    }
+  });
+
+  it("fails to spend unknown or missing manually selected utxos", () => {
+    expect.assertions(5);
+
+    const recipient = new Recipient(
+      recipientAddress.script_pubkey,
+      Amount.from_sat(BigInt(1000))
+    );
+
+    try {
+      wallet
+        .build_tx()
+        .add_recipient(recipient)
+        .manually_selected_only()
+        .finish();
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect(error.code).toBe(BdkErrorCode.NoUtxosSelected);
+    }
+
+    const outpoint =
+      "0000000000000000000000000000000000000000000000000000000000000001:0";
+    try {
+      wallet
+        .build_tx()
+        .add_recipient(recipient)
+        .add_utxo(OutPoint.from_string(outpoint))
+        .finish();
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+
+      const { code, data } = error;
+      expect(code).toBe(BdkErrorCode.UnknownUtxo);
+      expect(data).toBe(outpoint);
+    }
+  });
+
+  it("fails to spend a foreign utxo without previous output", () => {
+    expect.assertions(2);
+
+    const outpoint = OutPoint.from_string(
+      "0000000000000000000000000000000000000000000000000000000000000001:0"
+    );
+
+    try {
+      wallet
+        .build_tx()
+        .add_foreign_utxo(outpoint, new PsbtInput(), BigInt(108))
+        .drain_to(recipientAddress.script_pubkey)
+        .finish();
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect(error.code).toBe(BdkErrorCode.MissingUtxo);
+    }
+  });
+
+  it("fails to add non-standard OP_RETURN data", () => {
+    expect.assertions(6);
+    try {
+      wallet
+        .build_tx()
+        .add_data(new Uint8Array(81))
+        .finish();
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+
+      const { code, data } = error;
+      expect(code).toBe(BdkErrorCode.DataTooLarge);
+      expect(data).toBe(80);
+    }
+
+    try {
+      wallet
+        .build_tx()
+        .add_data(new Uint8Array(10))
+        .add_data(new Uint8Array(10))
+        .finish();
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+
+      const { code, data } = error;
+      expect(code).toBe(BdkErrorCode.MultipleDataOutputs);
+      expect(data).toBe(2);
+    }
+  });
+
+  it("fails to signal RBF with a final sequence", () => {
+    expect.assertions(3);
+    try {
+      wallet
+        .build_tx()
+        .enable_rbf_with_sequence(0xfffffffe)
+        .drain_to(recipientAddress.script_pubkey)
+        .finish();
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+
+      const { code, data } = error;
+      expect(code).toBe(BdkErrorCode.RbfSequence);
+      expect(data).toBe(0xfffffffe);
+    }
+  });
+
+  it("fails to build with an invalid current height", () => {
+    expect.assertions(2);
+    try {
+      wallet
+        .build_tx()
+        .current_height(500_000_000)
+        .drain_to(recipientAddress.script_pubkey)
+        .finish();
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect(error.code).toBe(BdkErrorCode.InvalidHeight);
+    }
+  });
+
+  it("previews the error of a transaction", () => {
+    const changeIndex = wallet.derivation_index("internal");
+
+    expect(wallet.build_tx().preview().error.code).toBe(
+      BdkErrorCode.NoRecipients
+    );
+
+    const preview = wallet
+      .build_tx()
+      .add_recipient(
+        new Recipient(recipientAddress.script_pubkey, Amount.from_sat(BigInt(1000)))
+      )
+      .preview();
+    expect(preview.error.code).toBe(BdkErrorCode.InsufficientFunds);
+    expect(preview.inputs).toEqual([]);
+    expect(wallet.derivation_index("internal")).toBe(changeIndex);
+  });
+
+  it("computes nothing sendable from an empty wallet", () => {
+    const max = wallet.max_sendable(
+      recipientAddress.script_pubkey,
+      new FeeRate(BigInt(1)),
+      new SendMaxOptions()
+    );
+    expect(max.to_sat()).toBe(BigInt(0));
+  });
+
+  it("spends the utxos having exactly the minimum confirmations", () => {
+    const fundedWallet = Wallet.create(network, externalDesc, internalDesc);
+    const funding = fundingTx(
+      fundedWallet.peek_address("external", 0).address.script_pubkey,
+      BigInt(100000)
+    );
+    const genesis = fundedWallet.latest_checkpoint;
+    const block = new BlockId(
+      1000,
+      "00000000000000000000000000000000000000000000000000000000000003e8"
+    );
+    // Confirmed at height 1000 with a tip at 1001, so 2 confirmations
+    fundedWallet.apply_update(
+      new UpdateBuilder()
+        .add_block(new BlockId(genesis.height, genesis.hash))
+        .add_block(
+          new BlockId(
+            1001,
+            "00000000000000000000000000000000000000000000000000000000000003e9"
+          )
+        )
+        .add_anchor(
+          funding.compute_txid(),
+          new ConfirmationBlockTime(block, BigInt(1700000000))
+        )
+        .add_tx(funding)
+        .build()
+    );
+    expect(fundedWallet.latest_checkpoint.height).toBe(1001);
+
+    const maxSendable = (minConfirmations: number) => {
+      const options = new SendMaxOptions();
+      options.min_confirmations = minConfirmations;
+      return fundedWallet
+        .max_sendable(
+          recipientAddress.script_pubkey,
+          new FeeRate(BigInt(1)),
+          options
+        )
+        .to_sat();
+    };
+
+    const max = maxSendable(2);
+    expect(max).toBeGreaterThan(BigInt(0));
+    expect(max).toBeLessThan(BigInt(100000));
+    expect(maxSendable(1)).toBe(max);
+    expect(maxSendable(3)).toBe(BigInt(0));
+  });
+
+  it("marks and unmarks addresses as used", () => {
+    const address = wallet.reveal_next_address("external");
+
+    expect(wallet.mark_used("external", address.index)).toBe(true);
+    expect(
+      wallet.list_unused_addresses("external").map((info) => info.index)
+    ).not.toContain(address.index);
+
+    expect(wallet.unmark_used("external", address.index)).toBe(true);
+    expect(
+      wallet.list_unused_addresses("external").map((info) => info.index)
+    ).toContain(address.index);
+  });
+
+  it("fails to abandon an unknown transaction", () => {
+    expect.assertions(2);
+
+    const txid = Txid.from_string(
+      "0000000000000000000000000000000000000000000000000000000000000001"
+    );
+
+    try {
+      wallet.abandon_tx(txid);
+    } catch (error) {
+      expect(error).toBeInstanceOf(BdkError);
+      expect(error.code).toBe(BdkErrorCode.TransactionNotFound);
+    }
+  });
+
+  describe("with several spending branches", () => {
+    // Spendable with the first key, or with the second key after 144 blocks
+    const policyDesc =
+      "wsh(or_d(pk(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/0'/0/*),and_v(v:pk(tpubDCkv2fHDfPg5hB6bFqJ4fNiins2Z8r5vKtD4xq5irCG2HsUXkgHYsj3gfGTdvAv41hoJeXjfxu7EBQqZMm6SVkxztKFtaaE7HuLdkuL7KNq/2/*),older(144))))";
+    let policyWallet: Wallet;
+
+    beforeAll(() => {
+      policyWallet = Wallet.create(network, policyDesc, internalDesc);
+    });
+
+    it("exposes the policy tree", () => {
+      const policy = policyWallet.policies("external");
+
+      expect(policy.item_type).toBe("THRESH");
+      expect(policy.threshold).toBe(1);
+      expect(policy.requires_path()).toBe(true);
+
+      const [key, recovery] = policy.children;
+      expect(key.item_type).toBe("ECDSASIGNATURE");
+      expect(key.item.fingerprint).toBe("27f9035f");
+      expect(recovery.threshold).toBe(2);
+      expect(recovery.children[1].item).toEqual({
+        type: "RELATIVETIMELOCK",
+        value: { Blocks: 144 },
+      });
+    });
+
+    it("requires a policy path to build a transaction", () => {
+      expect.assertions(2);
+
+      const recipient = new Recipient(
+        recipientAddress.script_pubkey,
+        Amount.from_sat(BigInt(1000))
+      );
+
+      try {
+        policyWallet.build_tx().add_recipient(recipient).finish();
+      } catch (error) {
+        expect(error).toBeInstanceOf(BdkError);
+        expect(error.code).toBe(BdkErrorCode.SpendingPolicyRequired);
+      }
+    });
+
+    it("spends with the branch chosen by the policy path", () => {
+      const funding = fundingTx(
+        policyWallet.peek_address("external", 0).address.script_pubkey,
+        BigInt(100000)
+      );
+
+      const genesis = policyWallet.latest_checkpoint;
+      const block = new BlockId(
+        1000,
+        "00000000000000000000000000000000000000000000000000000000000003e8"
+      );
+      policyWallet.apply_update(
+        new UpdateBuilder()
+          .add_block(new BlockId(genesis.height, genesis.hash))
+          .add_anchor(
+            funding.compute_txid(),
+            new ConfirmationBlockTime(block, BigInt(1700000000))
+          )
+          .add_tx(funding)
+          .build()
+      );
+      expect(policyWallet.balance.confirmed.to_sat()).toBe(BigInt(100000));
+
+      const policy = policyWallet.policies("external");
+      const build = (path: number[], id = policy.id) =>
+        policyWallet
+          .build_tx()
+          .policy_path({ [id]: path }, "external")
+          .add_recipient(
+            new Recipient(
+              recipientAddress.script_pubkey,
+              Amount.from_sat(BigInt(1000))
+            )
+          )
+          .finish().unsigned_tx;
+
+      // The key branch has no timelock, the inputs only signal RBF
+      const keyPath = build([0]);
+      expect(keyPath.input.map((txin) => txin.sequence)).toEqual([0xfffffffd]);
+
+      // The recovery branch needs the relative timelock of 144 blocks on the inputs
+      const recoveryPath = build([1]);
+      expect(recoveryPath.input.map((txin) => txin.sequence)).toEqual([144]);
+
+      const errorCode = (create: () => unknown) => {
+        try {
+          create();
+        } catch (error) {
+          return error.code;
+        }
+      };
+      expect(errorCode(() => build([0], "unknown"))).toBe(BdkErrorCode.Policy);
+      expect(errorCode(() => build([2]))).toBe(BdkErrorCode.Policy);
+    });
+  });
 });